@@ -0,0 +1,28 @@
+#!/usr/bin/env bash
+# times each engine on the scripts given (bench/calls.sk if none are), best of 5 runs, and how many times faster
+# the vm came out. run it from the top of the repo: bash bench/run.sh [script.sk...]
+set -euo pipefail
+
+cargo build --release --quiet
+bin=target/release/scriptinglang
+
+best_ms() {
+    local best=""
+    for _ in 1 2 3 4 5; do
+        local start end ms
+        start=$(date +%s%N)
+        "$bin" "$@" > /dev/null
+        end=$(date +%s%N)
+        ms=$(( (end - start) / 1000000 ))
+        if [ -z "$best" ] || [ "$ms" -lt "$best" ]; then
+            best=$ms
+        fi
+    done
+    echo "$best"
+}
+
+for script in "${@:-bench/calls.sk}"; do
+    vm=$(best_ms "$script")
+    tree=$(best_ms --tree-walk "$script")
+    echo "$script: vm ${vm}ms, tree walker ${tree}ms, $(( tree / (vm > 0 ? vm : 1) ))x"
+done
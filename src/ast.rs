@@ -0,0 +1,388 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum ASTNode {
+    //at the end of a "branch" of our tree.
+    None,
+    //the "atoms" of the language.
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Boolean(bool),
+    //"a {b} c" - the pieces in order, the literal ones as Text
+    Interpolated(Vec<Node>),
+    //"{x:>8.2}" - the x, and what came after the colon
+    Formatted(Box<Node>, FormatSpec),
+    //the str is the id
+    Variable(String),
+    //[a, b, c]
+    ListLiteral(Vec<Node>),
+    //list | index
+    Index(Box<Node>, Box<Node>),
+    //list | from | to - either bound can be None, for the start or end of the list
+    Slice(Box<Node>, Box<Node>, Box<Node>),
+    //{key: value, ...} - the keys are expressions too
+    MapLiteral(Vec<(Node, Node)>),
+    //thing.name
+    Field(Box<Node>, String),
+    //struct name | fields, in the order they're written
+    StructLiteral(String, Vec<(String, Node)>),
+    //assignment - id and the expression to be assigned.
+    Set(Box<Node>, Box<Node>),
+    //like set, but for vars (or elements, or fields) that already exist
+    Change(Box<Node>, Box<Node>),
+    //operations
+    Add(Box<Node>, Box<Node>),
+    AddEq(Box<Node>, Box<Node>),
+    Sub(Box<Node>, Box<Node>),
+    SubEq(Box<Node>, Box<Node>),
+    Mul(Box<Node>, Box<Node>),
+    MulEq(Box<Node>, Box<Node>),
+    Div(Box<Node>, Box<Node>),
+    DivEq(Box<Node>, Box<Node>),
+    EqCheck(Box<Node>, Box<Node>),
+    NeqCheck(Box<Node>, Box<Node>),
+    GtCheck(Box<Node>, Box<Node>),
+    LtCheck(Box<Node>, Box<Node>),
+    GteCheck(Box<Node>, Box<Node>),
+    LteCheck(Box<Node>, Box<Node>),
+    Modulo(Box<Node>, Box<Node>),
+    ModuloEq(Box<Node>, Box<Node>),
+    Pow(Box<Node>, Box<Node>),
+    PowEq(Box<Node>, Box<Node>),
+    FloorDiv(Box<Node>, Box<Node>),
+    //bitwise operators
+    BitAnd(Box<Node>, Box<Node>),
+    BitOr(Box<Node>, Box<Node>),
+    BitXor(Box<Node>, Box<Node>),
+    ShiftLeft(Box<Node>, Box<Node>),
+    ShiftRight(Box<Node>, Box<Node>),
+    //these two only look at the right side if the left doesn't settle it, and give back whichever side did
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    //unary operators
+    Negate(Box<Node>),
+    Not(Box<Node>),
+    BitNot(Box<Node>),
+    //functions
+    FunctionCall(Box<Node>, Vec<Node>),
+    //id | paramlist | body | its ## doc comment
+    FunctionDecl(Box<Node>, Vec<Node>, Vec<Node>, Option<String>),
+    //fn(paramlist) { body } - an anonymous function, as a value
+    FunctionExpr(Vec<Node>, Vec<Node>),
+    //thing | method name | args - thing.name(args)
+    MethodCall(Box<Node>, String, Vec<Node>),
+    //= Struct things
+    //name | field names
+    StructDecl(String, Vec<String>),
+    //struct name | its methods, as FunctionDecls
+    ImplBlock(String, Vec<Node>),
+    //condition | if body | has an else clause? | else body
+    IfStatement(Box<Node>, Vec<Node>, bool, Vec<Node>),
+    //= Module things
+    //import "path" as name
+    Import(String, Box<Node>),
+    //from "path" import names - each one a Variable
+    ImportFrom(String, Vec<Node>),
+    //= Loop things
+    //iter var name, lower bound, upper bound, body
+    IncrementingLoop(Box<Node>, Box<Node>, Box<Node>, Vec<Node>),
+    //condition, body
+    ConditionalLoop(Box<Node>, Vec<Node>),
+    //iter var name, second var name for `loop k, v in`, the list or map, body
+    EachLoop(Box<Node>, Option<Box<Node>>, Box<Node>, Vec<Node>),
+    //escape!
+    LoopBreak,
+    LoopContinue,
+    //the value to hand back
+    Return(Box<Node>),
+}
+
+impl ASTNode {
+    //the things that are only ever run for what they do, never for a value
+    pub fn is_statement(&self) -> bool {
+        matches!(
+            self,
+            ASTNode::Set(..)
+                | ASTNode::Change(..)
+                | ASTNode::AddEq(..)
+                | ASTNode::SubEq(..)
+                | ASTNode::MulEq(..)
+                | ASTNode::DivEq(..)
+                | ASTNode::ModuloEq(..)
+                | ASTNode::PowEq(..)
+                | ASTNode::FunctionDecl(..)
+                | ASTNode::StructDecl(..)
+                | ASTNode::ImplBlock(..)
+                | ASTNode::Import(..)
+                | ASTNode::ImportFrom(..)
+                | ASTNode::IfStatement(..)
+                | ASTNode::IncrementingLoop(..)
+                | ASTNode::ConditionalLoop(..)
+                | ASTNode::EachLoop(..)
+                | ASTNode::LoopBreak
+                | ASTNode::LoopContinue
+                | ASTNode::Return(_)
+        )
+    }
+}
+
+//how an interpolated value gets laid out - [<^>][0][width][.precision], all optional
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FormatSpec {
+    //'<', '^' or '>'. left to itself, numbers go on the right and everything else on the left
+    pub align: Option<char>,
+    //pad numbers with zeros after the sign, rather than spaces in front
+    pub zero: bool,
+    pub width: usize,
+    //digits after the point for numbers, how much to keep of text
+    pub precision: Option<usize>,
+}
+
+//an ASTNode along with where in the source it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub ast: ASTNode,
+    pub span: Span,
+}
+
+impl Node {
+    pub fn new(ast: ASTNode, span: Span) -> Node {
+        Node { ast, span }
+    }
+}
+
+//byte offsets into the (preprocessed) source
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+    //the smallest span covering both of us
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+//pull the name out of a Variable node
+pub(crate) fn variable_name(node: Node) -> String {
+    match node.ast {
+        ASTNode::Variable(id) => id,
+        _ => String::new(),
+    }
+}
+
+//the name in a FunctionDecl node
+pub(crate) fn function_name(id: &Node) -> &str {
+    match &id.ast {
+        ASTNode::Text(idstr) => idstr,
+        _ => "",
+    }
+}
+
+//the globals a file's top level defines - its lets, its fns and whatever it imports. for a module, these are what
+//it exports.
+pub(crate) fn declared_names(trees: &[Node]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for tree in trees {
+        match &tree.ast {
+            ASTNode::Set(id, _) | ASTNode::Import(_, id) => names.push(variable_name(*id.clone())),
+            ASTNode::FunctionDecl(id, ..) => names.push(function_name(id).to_string()),
+            ASTNode::ImportFrom(_, ids) => names.extend(ids.iter().map(|id| variable_name(id.clone()))),
+            _ => {}
+        }
+    }
+    names
+}
+
+//an indented outline of a tree, for :ast
+pub fn dump_ast(tree: &Node, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let pair = |name: &str, p1: &Node, p2: &Node, out: &mut String| {
+        *out += &format!("{}{}\n", indent, name);
+        dump_ast(p1, depth + 1, out);
+        dump_ast(p2, depth + 1, out);
+    };
+    let block = |name: &str, body: &[Node], out: &mut String| {
+        *out += &format!("{}  {}:\n", indent, name);
+        for tree in body {
+            dump_ast(tree, depth + 2, out);
+        }
+    };
+    match &tree.ast {
+        ASTNode::None => *out += &format!("{}None\n", indent),
+        ASTNode::Text(text) => *out += &format!("{}Text {:?}\n", indent, text),
+        ASTNode::Int(num) => *out += &format!("{}Int {}\n", indent, num),
+        ASTNode::Float(num) => *out += &format!("{}Float {:?}\n", indent, num),
+        ASTNode::Boolean(b) => *out += &format!("{}Boolean {}\n", indent, b),
+        ASTNode::Interpolated(parts) => {
+            *out += &format!("{}Interpolated\n", indent);
+            for part in parts {
+                dump_ast(part, depth + 1, out);
+            }
+        }
+        ASTNode::Formatted(p1, spec) => {
+            *out += &format!("{}Formatted {:?}\n", indent, spec);
+            dump_ast(p1, depth + 1, out);
+        }
+        ASTNode::Variable(id) => *out += &format!("{}Variable {}\n", indent, id),
+        ASTNode::ListLiteral(items) => {
+            *out += &format!("{}ListLiteral\n", indent);
+            for item in items {
+                dump_ast(item, depth + 1, out);
+            }
+        }
+        ASTNode::Index(p1, p2) => pair("Index", p1, p2, out),
+        ASTNode::MapLiteral(entries) => {
+            *out += &format!("{}MapLiteral\n", indent);
+            for (key, val) in entries {
+                dump_ast(key, depth + 1, out);
+                dump_ast(val, depth + 2, out);
+            }
+        }
+        ASTNode::Field(p1, name) => {
+            *out += &format!("{}Field {}\n", indent, name);
+            dump_ast(p1, depth + 1, out);
+        }
+        ASTNode::StructLiteral(name, fields) => {
+            *out += &format!("{}StructLiteral {}\n", indent, name);
+            for (field, val) in fields {
+                *out += &format!("{}  {}:\n", indent, field);
+                dump_ast(val, depth + 2, out);
+            }
+        }
+        ASTNode::Slice(list, from, to) => {
+            *out += &format!("{}Slice\n", indent);
+            dump_ast(list, depth + 1, out);
+            dump_ast(from, depth + 1, out);
+            dump_ast(to, depth + 1, out);
+        }
+        ASTNode::Set(p1, p2) => pair("Set", p1, p2, out),
+        ASTNode::Change(p1, p2) => pair("Change", p1, p2, out),
+        ASTNode::Add(p1, p2) => pair("Add", p1, p2, out),
+        ASTNode::AddEq(p1, p2) => pair("AddEq", p1, p2, out),
+        ASTNode::Sub(p1, p2) => pair("Sub", p1, p2, out),
+        ASTNode::SubEq(p1, p2) => pair("SubEq", p1, p2, out),
+        ASTNode::Mul(p1, p2) => pair("Mul", p1, p2, out),
+        ASTNode::MulEq(p1, p2) => pair("MulEq", p1, p2, out),
+        ASTNode::Div(p1, p2) => pair("Div", p1, p2, out),
+        ASTNode::DivEq(p1, p2) => pair("DivEq", p1, p2, out),
+        ASTNode::EqCheck(p1, p2) => pair("EqCheck", p1, p2, out),
+        ASTNode::NeqCheck(p1, p2) => pair("NeqCheck", p1, p2, out),
+        ASTNode::GtCheck(p1, p2) => pair("GtCheck", p1, p2, out),
+        ASTNode::LtCheck(p1, p2) => pair("LtCheck", p1, p2, out),
+        ASTNode::GteCheck(p1, p2) => pair("GteCheck", p1, p2, out),
+        ASTNode::LteCheck(p1, p2) => pair("LteCheck", p1, p2, out),
+        ASTNode::Modulo(p1, p2) => pair("Modulo", p1, p2, out),
+        ASTNode::ModuloEq(p1, p2) => pair("ModuloEq", p1, p2, out),
+        ASTNode::Pow(p1, p2) => pair("Pow", p1, p2, out),
+        ASTNode::PowEq(p1, p2) => pair("PowEq", p1, p2, out),
+        ASTNode::FloorDiv(p1, p2) => pair("FloorDiv", p1, p2, out),
+        ASTNode::BitAnd(p1, p2) => pair("BitAnd", p1, p2, out),
+        ASTNode::BitOr(p1, p2) => pair("BitOr", p1, p2, out),
+        ASTNode::BitXor(p1, p2) => pair("BitXor", p1, p2, out),
+        ASTNode::ShiftLeft(p1, p2) => pair("ShiftLeft", p1, p2, out),
+        ASTNode::ShiftRight(p1, p2) => pair("ShiftRight", p1, p2, out),
+        ASTNode::And(p1, p2) => pair("And", p1, p2, out),
+        ASTNode::Or(p1, p2) => pair("Or", p1, p2, out),
+        ASTNode::Negate(p1) => {
+            *out += &format!("{}Negate\n", indent);
+            dump_ast(p1, depth + 1, out);
+        }
+        ASTNode::Not(p1) => {
+            *out += &format!("{}Not\n", indent);
+            dump_ast(p1, depth + 1, out);
+        }
+        ASTNode::BitNot(p1) => {
+            *out += &format!("{}BitNot\n", indent);
+            dump_ast(p1, depth + 1, out);
+        }
+        ASTNode::FunctionCall(callee, params) => {
+            //calling something by name is common enough to get a line of its own
+            if let ASTNode::Variable(id) = &callee.ast {
+                *out += &format!("{}FunctionCall {}\n", indent, id);
+            } else {
+                *out += &format!("{}FunctionCall\n", indent);
+                block("callee", std::slice::from_ref(callee), out);
+            }
+            for arg in params {
+                dump_ast(arg, depth + 1, out);
+            }
+        }
+        ASTNode::FunctionDecl(id, params, body, doc) => {
+            let params: Vec<String> = params.iter().map(|p| variable_name(p.clone())).collect();
+            *out += &format!("{}FunctionDecl {}({})\n", indent, function_name(id), params.join(", "));
+            for line in doc.iter().flat_map(|doc| doc.lines()) {
+                *out += &format!("{}  ## {}\n", indent, line);
+            }
+            block("body", body, out);
+        }
+        ASTNode::FunctionExpr(params, body) => {
+            let params: Vec<String> = params.iter().map(|p| variable_name(p.clone())).collect();
+            *out += &format!("{}FunctionExpr ({})\n", indent, params.join(", "));
+            block("body", body, out);
+        }
+        ASTNode::MethodCall(target, name, params) => {
+            *out += &format!("{}MethodCall {}\n", indent, name);
+            dump_ast(target, depth + 1, out);
+            block("args", params, out);
+        }
+        ASTNode::StructDecl(name, fields) => {
+            *out += &format!("{}StructDecl {} {{ {} }}\n", indent, name, fields.join(", "));
+        }
+        ASTNode::ImplBlock(name, methods) => {
+            *out += &format!("{}ImplBlock {}\n", indent, name);
+            for method in methods {
+                dump_ast(method, depth + 1, out);
+            }
+        }
+        ASTNode::Import(path, name) => {
+            *out += &format!("{}Import {:?} as {}\n", indent, path, variable_name(*name.clone()));
+        }
+        ASTNode::ImportFrom(path, names) => {
+            let names: Vec<String> = names.iter().map(|name| variable_name(name.clone())).collect();
+            *out += &format!("{}ImportFrom {:?}: {}\n", indent, path, names.join(", "));
+        }
+        ASTNode::IfStatement(condition, ifbody, haselse, elsebody) => {
+            *out += &format!("{}IfStatement\n", indent);
+            dump_ast(condition, depth + 1, out);
+            block("then", ifbody, out);
+            if *haselse {
+                block("else", elsebody, out);
+            }
+        }
+        ASTNode::IncrementingLoop(itername, lowerbound, upperbound, loopbody) => {
+            *out += &format!(
+                "{}IncrementingLoop {}\n",
+                indent,
+                variable_name(*itername.clone())
+            );
+            dump_ast(lowerbound, depth + 1, out);
+            dump_ast(upperbound, depth + 1, out);
+            block("body", loopbody, out);
+        }
+        ASTNode::ConditionalLoop(condition, loopbody) => {
+            *out += &format!("{}ConditionalLoop\n", indent);
+            dump_ast(condition, depth + 1, out);
+            block("body", loopbody, out);
+        }
+        ASTNode::EachLoop(itername, valname, list, loopbody) => {
+            let mut names = variable_name(*itername.clone());
+            if let Some(valname) = valname {
+                names += &format!(", {}", variable_name(*valname.clone()));
+            }
+            *out += &format!("{}EachLoop {}\n", indent, names);
+            dump_ast(list, depth + 1, out);
+            block("body", loopbody, out);
+        }
+        ASTNode::LoopBreak => *out += &format!("{}LoopBreak\n", indent),
+        ASTNode::LoopContinue => *out += &format!("{}LoopContinue\n", indent),
+        ASTNode::Return(valexp) => {
+            *out += &format!("{}Return\n", indent);
+            dump_ast(valexp, depth + 1, out);
+        }
+    }
+}
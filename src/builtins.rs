@@ -0,0 +1,233 @@
+use crate::error::{RuntimeError, RuntimeErrorKind};
+use crate::value::{list_position, map_key, whole_index, Value};
+use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+//what a native function looks like from rust. any span or stack on the error it gives back gets replaced
+//with the call's, since the function itself has no idea where it was called from.
+pub(crate) type NativeFn = dyn Fn(&[Value]) -> Result<Value, RuntimeError>;
+
+//a function written in rust rather than in script - print and friends, and anything an embedder registers
+pub(crate) struct Native {
+    pub(crate) name: String,
+    pub(crate) arity: usize,
+    pub(crate) func: Rc<NativeFn>,
+}
+
+impl std::fmt::Debug for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Native({}/{})", self.name, self.arity)
+    }
+}
+
+pub(crate) fn call_native(native: &Native, args: &[Value]) -> Result<Value, RuntimeErrorKind> {
+    if args.len() != native.arity {
+        return Err(RuntimeErrorKind::ArityMismatch {
+            function: native.name.clone(),
+            expected: native.arity,
+            found: args.len(),
+        });
+    }
+    (native.func)(args).map_err(|err| err.kind)
+}
+
+//the list a native was handed, or an error saying it wanted one
+pub(crate) fn list_arg<'a>(function: &str, val: &'a Value) -> Result<&'a RefCell<Vec<Value>>, RuntimeError> {
+    match val {
+        Value::List(items) => Ok(items),
+        val => Err(RuntimeError::new(RuntimeErrorKind::TypeError(format!(
+            "`{}` takes a list, not {}",
+            function,
+            val.type_name()
+        )))),
+    }
+}
+
+//the same, for maps
+pub(crate) fn map_arg<'a>(
+    function: &str,
+    val: &'a Value,
+) -> Result<&'a RefCell<IndexMap<String, Value>>, RuntimeError> {
+    match val {
+        Value::Map(entries) => Ok(entries),
+        val => Err(RuntimeError::new(RuntimeErrorKind::TypeError(format!(
+            "`{}` takes a map, not {}",
+            function,
+            val.type_name()
+        )))),
+    }
+}
+
+//the functions every interpreter starts out with. `scriptArgs` being whatever came after `--` on the command line.
+pub(crate) fn standard_natives(scriptArgs: Vec<String>) -> Vec<Native> {
+    let scriptArgs = Rc::new(scriptArgs);
+    let native = |name: &str, arity: usize, func: Rc<NativeFn>| Native {
+        name: name.to_string(),
+        arity,
+        func,
+    };
+    vec![
+        native(
+            "print",
+            1,
+            Rc::new(|args| {
+                println!("{}", args[0]);
+                Ok(Value::None)
+            }),
+        ),
+        //how many arguments the script was given on the command line, and the text of one of them
+        native("argc", 0, {
+            let scriptArgs = scriptArgs.clone();
+            Rc::new(move |_| Ok(Value::Int(scriptArgs.len() as i64)))
+        }),
+        native(
+            "arg",
+            1,
+            Rc::new(move |args| match &args[0] {
+                //asking for one past the end just gets you none
+                Value::Int(n) if *n >= 0 => Ok(scriptArgs
+                    .get(*n as usize)
+                    .map(|arg| Value::Text(arg.clone()))
+                    .unwrap_or(Value::None)),
+                val => Err(RuntimeError::new(RuntimeErrorKind::TypeError(format!(
+                    "`arg` takes an int, not {}",
+                    val.type_name()
+                )))),
+            }),
+        ),
+        //= Numbers
+        //int(x) drops anything after the point, and reads text like "42"
+        native(
+            "int",
+            1,
+            Rc::new(|args| match &args[0] {
+                Value::Int(n) => Ok(Value::Int(*n)),
+                //i64::MAX as f64 rounds up to 2^63, so the top end has to be exclusive
+                Value::Float(n) if n.is_finite() && *n >= i64::MIN as f64 && *n < i64::MAX as f64 => {
+                    Ok(Value::Int(*n as i64))
+                }
+                Value::Float(n) => Err(RuntimeError::new(RuntimeErrorKind::TypeError(format!(
+                    "cannot turn {:?} into an int",
+                    n
+                )))),
+                Value::Text(text) => text.trim().parse().map(Value::Int).map_err(|_| {
+                    RuntimeError::new(RuntimeErrorKind::TypeError(format!(
+                        "cannot turn {:?} into an int",
+                        text
+                    )))
+                }),
+                Value::Boolean(b) => Ok(Value::Int(*b as i64)),
+                val => Err(RuntimeError::new(RuntimeErrorKind::TypeError(format!(
+                    "`int` takes a number, text or a boolean, not {}",
+                    val.type_name()
+                )))),
+            }),
+        ),
+        native(
+            "float",
+            1,
+            Rc::new(|args| match &args[0] {
+                Value::Int(n) => Ok(Value::Float(*n as f64)),
+                Value::Float(n) => Ok(Value::Float(*n)),
+                Value::Text(text) => text.trim().parse().map(Value::Float).map_err(|_| {
+                    RuntimeError::new(RuntimeErrorKind::TypeError(format!(
+                        "cannot turn {:?} into a float",
+                        text
+                    )))
+                }),
+                Value::Boolean(b) => Ok(Value::Float(*b as i64 as f64)),
+                val => Err(RuntimeError::new(RuntimeErrorKind::TypeError(format!(
+                    "`float` takes a number, text or a boolean, not {}",
+                    val.type_name()
+                )))),
+            }),
+        ),
+        //= Lists
+        native(
+            "len",
+            1,
+            Rc::new(|args| match &args[0] {
+                Value::List(items) => Ok(Value::Int(items.borrow().len() as i64)),
+                Value::Map(entries) => Ok(Value::Int(entries.borrow().len() as i64)),
+                Value::Text(text) => Ok(Value::Int(text.chars().count() as i64)),
+                val => Err(RuntimeError::new(RuntimeErrorKind::TypeError(format!(
+                    "`len` takes a list, a map or text, not {}",
+                    val.type_name()
+                )))),
+            }),
+        ),
+        native(
+            "push",
+            2,
+            Rc::new(|args| {
+                list_arg("push", &args[0])?.borrow_mut().push(args[1].clone());
+                Ok(Value::None)
+            }),
+        ),
+        //popping an empty list just gets you none
+        native(
+            "pop",
+            1,
+            Rc::new(|args| Ok(list_arg("pop", &args[0])?.borrow_mut().pop().unwrap_or(Value::None))),
+        ),
+        //insert(xs, i, v) puts v where xs[i] is now, moving everything from there on up one. i can be len(xs), for the end.
+        native(
+            "insert",
+            3,
+            Rc::new(|args| {
+                let mut items = list_arg("insert", &args[0])?.borrow_mut();
+                let index = whole_index(&args[1]).map_err(RuntimeError::new)?;
+                let length = items.len();
+                let position = if index < 0 { index + length as i64 } else { index };
+                if position < 0 || position > length as i64 {
+                    return Err(RuntimeError::new(RuntimeErrorKind::IndexOutOfBounds { index, length }));
+                }
+                items.insert(position as usize, args[2].clone());
+                Ok(Value::None)
+            }),
+        ),
+        //remove(xs, i) takes xs[i] out and hands it back. remove(m, key) does the same for a map.
+        native(
+            "remove",
+            2,
+            Rc::new(|args| {
+                if let Value::Map(entries) = &args[0] {
+                    let key = map_key(&args[1]).map_err(RuntimeError::new)?;
+                    let removed = entries.borrow_mut().shift_remove(key);
+                    return removed.ok_or_else(|| RuntimeError::new(RuntimeErrorKind::MissingKey(key.to_string())));
+                }
+                let mut items = list_arg("remove", &args[0])?.borrow_mut();
+                let position = list_position(&args[1], items.len()).map_err(RuntimeError::new)?;
+                Ok(items.remove(position))
+            }),
+        ),
+        //= Maps
+        native(
+            "keys",
+            1,
+            Rc::new(|args| {
+                let entries = map_arg("keys", &args[0])?.borrow();
+                Ok(Value::list(
+                    entries.keys().map(|key| Value::Text(key.clone())).collect(),
+                ))
+            }),
+        ),
+        native(
+            "values",
+            1,
+            Rc::new(|args| {
+                let entries = map_arg("values", &args[0])?.borrow();
+                Ok(Value::list(entries.values().cloned().collect()))
+            }),
+        ),
+        native(
+            "has",
+            2,
+            Rc::new(|args| {
+                let key = map_key(&args[1]).map_err(RuntimeError::new)?;
+                Ok(Value::Boolean(map_arg("has", &args[0])?.borrow().contains_key(key)))
+            }),
+        ),
+    ]
+}
@@ -0,0 +1,954 @@
+use crate::ast::{function_name, variable_name, ASTNode, FormatSpec, Node, Span};
+use crate::value::{struct_key, BinOp, Value};
+use std::rc::Rc;
+
+//= Bytecode
+//the tree walker in exec.rs is the reference. everything down here should behave exactly like it does,
+//just without walking (and cloning) the tree again every time a loop comes back around.
+
+//one instruction. operands are indexes into the constant/local/global/function tables, or absolute jump targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Op {
+    //push constants[i]
+    Constant(u32),
+    Pop,
+    //push copies of the top n values, in the same order
+    Dup(u32),
+    //locals are slots on the stack, counted from the frame's base
+    GetLocal(u32),
+    SetLocal(u32),
+    //globals are resolved to slots at compile time, but only exist once a let has run
+    GetGlobal(u32),
+    DefineGlobal(u32),
+    SetGlobal(u32),
+    Binary(BinOp),
+    Negate,
+    Not,
+    BitNot,
+    Jump(u32),
+    //both pop the condition
+    JumpIfFalsy(u32),
+    JumpIfTruthy(u32),
+    //and/or: jump with the value left where it is if it settles things, otherwise pop it and carry on to the other side
+    JumpIfFalsyOrPop(u32),
+    JumpIfTruthyOrPop(u32),
+    //a comparison and a jump in one go, taken when the comparison comes out as `when`.
+    //comparisons only ever give back booleans, so this is right for both kinds of condition.
+    CompareJump { op: BinOp, when: bool, to: u32 },
+    //pops the bounds of a `loop x in a to b` into the counter slot and the one after it
+    RangeInit(u32),
+    //copies the counter into the loop variable, bumps it and jumps back to the body, or falls through once it hits the end
+    RangeNext { counter: u32, var: u32, body: u32 },
+    //pops a list or map for `loop x in xs` into the items slot (as a copy), with the position in the slot after it
+    EachInit(u32),
+    //same as RangeNext, but copying out the next entry - into var, and for `loop k, v in` the slot after it too
+    EachNext { items: u32, var: u32, pair: bool, body: u32 },
+    //pops that many values into a new list
+    MakeList(u32),
+    //fails unless the value on top could be a map key, leaving it there. a map literal checks each key as soon as
+    //it has it, so a bad one stops things before the values after it get worked out.
+    MapKey,
+    //pops that many key, value pairs into a new map
+    MakeMap(u32),
+    //thing -> thing.name, with the name as constants[i]
+    GetField(u32),
+    //thing, value -> nothing
+    SetField(u32),
+    //add structs[i] to the vm's structs
+    DefineStruct(u32),
+    //pops a value for each of structs[i]'s fields into a new instance of the struct by that name
+    MakeStruct(u32),
+    //pops a closure into the methods of the struct named by constants[ty], as constants[name]
+    DefineMethod { ty: u32, name: u32 },
+    //thing.name(args), with the thing sitting under the arguments. looks the method up, then calls it like Call would.
+    CallMethod { name: u32, argc: u32 },
+    //pops that many values, pushing them back as one bit of text
+    Concat(u32),
+    //value -> value laid out according to formats[i]
+    Format(u32),
+    //list, index -> item
+    Index,
+    //list, index, value -> nothing
+    SetIndex,
+    //list, from, to -> new list
+    Slice,
+    //the variables a closure reaches out of its own function for, by their index in Proto.upvalues
+    GetUpvalue(u32),
+    SetUpvalue(u32),
+    //a global that's about to be called. same as GetGlobal, but missing is an undefined function.
+    GetGlobalFunction(u32),
+    //make a closure out of protos[i], capturing whatever it needs from the function making it
+    Closure(u32),
+    //the locals from this slot up are going away, so any closures still using them get their own copies
+    CloseUpvalues(u32),
+    //the callee sits on the stack under its arguments
+    Call { argc: u32 },
+    //hand the top of the stack back to whoever called us
+    Return,
+}
+
+//a compiled function (or script) - its code, and everything that code refers to
+#[derive(Debug, Default)]
+pub(crate) struct Proto {
+    pub(crate) name: String,
+    pub(crate) params: Vec<String>,
+    //how many local slots a call needs, params included
+    pub(crate) locals: usize,
+    pub(crate) code: Vec<Op>,
+    //where each instruction came from, for errors
+    pub(crate) spans: Vec<Span>,
+    pub(crate) constants: Vec<Value>,
+    pub(crate) protos: Vec<Rc<Proto>>,
+    //a struct's name and some of its fields: all of them for a declaration, the ones given (in order) for a literal
+    pub(crate) structs: Vec<(String, Vec<String>)>,
+    //the specs from "{x:spec}"
+    pub(crate) formats: Vec<FormatSpec>,
+    //where each of its upvalues comes from when a closure gets made: (true, slot) for one of the enclosing
+    //function's locals, (false, index) for one of the enclosing function's own upvalues
+    pub(crate) upvalues: Vec<(bool, u32)>,
+}
+
+//the function we're in the middle of emitting
+#[derive(Debug, Default)]
+pub(crate) struct FnCompiler {
+    pub(crate) proto: Proto,
+    //block scopes, innermost last. each one maps names to local slots.
+    //the script's outermost level has no scope at all - a let there makes a global.
+    pub(crate) scopes: Vec<Vec<Local>>,
+    pub(crate) nextSlot: u32,
+    //the loops we're inside of, innermost last
+    pub(crate) loops: Vec<LoopJumps>,
+    //functions whose bodies we haven't compiled yet, see emit_closure
+    pub(crate) pending: Vec<PendingFunction>,
+}
+
+//a function whose closure has been emitted, but whose body waits until the end of the scope it's in.
+//by then every local in that scope has been declared, so the body can see all of them, same as it would
+//when the tree walker looks them up at call time.
+#[derive(Debug)]
+pub(crate) struct PendingFunction {
+    pub(crate) proto: u32,
+    pub(crate) name: String,
+    pub(crate) params: Vec<Node>,
+    pub(crate) body: Vec<Node>,
+    pub(crate) span: Span,
+    //how many scopes were open when it was made
+    pub(crate) depth: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Local {
+    pub(crate) name: String,
+    pub(crate) slot: u32,
+    //whether a closure has reached in for it, meaning it has to be closed over once its scope ends
+    pub(crate) captured: bool,
+}
+
+//the breaks and continues in a loop body, waiting to find out where the loop ends and where it checks its condition
+#[derive(Debug, Default)]
+pub(crate) struct LoopJumps {
+    pub(crate) breaks: Vec<usize>,
+    pub(crate) continues: Vec<usize>,
+    //the first slot the body's locals use. a break or continue leaves all of them behind.
+    pub(crate) firstSlot: u32,
+}
+
+//where a name turned out to live
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Resolved {
+    Local(u32),
+    Upvalue(u32),
+    Global(u32),
+}
+
+//a module's path, and the globals its top level defines (which are what it exports). every global its code
+//names goes in a slot called "path::id", so two modules (or a module and the file importing it) can use the same
+//names without treading on each other, and a module can't see anyone else's globals at all. the natives get
+//copied into its slots when it's entered.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ModuleScope {
+    pub(crate) path: String,
+    pub(crate) names: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct CompilerState {
+    //names of the globals, by slot. these stick around between compiles so a vm can keep running
+    //code from the same CompilerState without its tables getting shuffled.
+    pub(crate) globalNames: Vec<String>,
+    //set while compiling a module, whose own globals live apart from everyone else's
+    pub(crate) module: Option<ModuleScope>,
+    pub(crate) current: FnCompiler,
+    //the functions current is nested inside of, outermost first
+    pub(crate) enclosing: Vec<FnCompiler>,
+}
+
+impl CompilerState {
+    pub(crate) fn emit(&mut self, op: Op, span: Span) -> usize {
+        let proto = &mut self.current.proto;
+        proto.code.push(op);
+        proto.spans.push(span);
+        proto.code.len() - 1
+    }
+
+    //address of the next instruction we emit
+    pub(crate) fn here(&self) -> u32 {
+        self.current.proto.code.len() as u32
+    }
+
+    pub(crate) fn emit_constant(&mut self, val: Value, span: Span) {
+        let index = self.constant_index(val);
+        self.emit(Op::Constant(index), span);
+    }
+
+    //where this name and list of fields is in the struct table, adding it if it isn't there yet
+    pub(crate) fn struct_index(&mut self, name: &str, fields: Vec<String>) -> u32 {
+        let name = self.struct_key(name);
+        let structs = &mut self.current.proto.structs;
+        match structs.iter().position(|(n, f)| *n == name && *f == fields) {
+            Some(index) => index as u32,
+            None => {
+                structs.push((name, fields));
+                (structs.len() - 1) as u32
+            }
+        }
+    }
+
+    //where `val` is in the constant table, adding it if it isn't there yet
+    pub(crate) fn constant_index(&mut self, val: Value) -> u32 {
+        let constants = &mut self.current.proto.constants;
+        //1 and 1.0 are equal, but they aren't the same constant
+        let same = |c: &Value| *c == val && std::mem::discriminant(c) == std::mem::discriminant(&val);
+        match constants.iter().position(same) {
+            Some(index) => index as u32,
+            None => {
+                constants.push(val);
+                (constants.len() - 1) as u32
+            }
+        }
+    }
+
+    //point the jump at `at` to wherever we are now
+    pub(crate) fn patch_jump(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.current.proto.code[at] {
+            Op::Jump(to)
+            | Op::JumpIfFalsy(to)
+            | Op::JumpIfTruthy(to)
+            | Op::JumpIfFalsyOrPop(to)
+            | Op::JumpIfTruthyOrPop(to)
+            | Op::CompareJump { to, .. } => *to = target,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn global_slot(&mut self, id: &str) -> u32 {
+        match &self.module {
+            Some(module) => intern(&mut self.globalNames, &format!("{}::{}", module.path, id)),
+            None => intern(&mut self.globalNames, id),
+        }
+    }
+
+    //what the struct `name` is filed under in the code being compiled right now
+    pub(crate) fn struct_key(&self, name: &str) -> String {
+        struct_key(self.module.as_ref().map(|module| module.path.as_str()), name)
+    }
+
+    //what the script called the global in `slot`, without the module it belongs to
+    pub(crate) fn global_name(&self, slot: u32) -> String {
+        let name = &self.globalNames[slot as usize];
+        name.rsplit("::").next().unwrap_or(name).to_string()
+    }
+
+    pub(crate) fn begin_scope(&mut self) {
+        self.current.scopes.push(vec![]);
+    }
+
+    //the slots a block used are free again once it's over
+    pub(crate) fn end_scope(&mut self) {
+        compile_pending(self.current.scopes.len(), self);
+        if let Some(scope) = self.current.scopes.pop() {
+            let captured = scope.iter().filter(|local| local.captured);
+            if let Some(first) = captured.map(|local| local.slot).min() {
+                self.emit(Op::CloseUpvalues(first), Span::default());
+            }
+            if let Some(first) = scope.iter().map(|local| local.slot).min() {
+                self.current.nextSlot = first;
+            }
+        }
+    }
+
+    //a let in a block. letting the same name twice in one block just reuses its slot, same as the tree walker's map would.
+    pub(crate) fn declare_local(&mut self, id: &str) -> u32 {
+        let existing = self
+            .current
+            .scopes
+            .last()
+            .and_then(|scope| scope.iter().rev().find(|local| local.name == id));
+        match existing {
+            Some(local) => local.slot,
+            None => self.declare_slot(id.to_string()),
+        }
+    }
+
+    //a fresh slot in the innermost scope. anonymous ones (named "") are for the compiler's own bookkeeping.
+    pub(crate) fn declare_slot(&mut self, id: String) -> u32 {
+        let slot = self.current.nextSlot;
+        self.current.nextSlot += 1;
+        let proto = &mut self.current.proto;
+        proto.locals = proto.locals.max(slot as usize + 1);
+        if let Some(scope) = self.current.scopes.last_mut() {
+            scope.push(Local {
+                name: id,
+                slot,
+                captured: false,
+            });
+        }
+        slot
+    }
+
+    //a local of ours, failing that one of a function we're nested in, failing that a global
+    pub(crate) fn resolve(&mut self, id: &str) -> Resolved {
+        if let Some(local) = find_local(&mut self.current, id) {
+            return Resolved::Local(local.slot);
+        }
+        match self.resolve_upvalue(self.enclosing.len(), id) {
+            Some(index) => Resolved::Upvalue(index),
+            None => Resolved::Global(self.global_slot(id)),
+        }
+    }
+
+    //the compiler for the function `level` deep, counting the script as 0 and current as the deepest
+    pub(crate) fn compiler_at(&mut self, level: usize) -> &mut FnCompiler {
+        if level == self.enclosing.len() {
+            &mut self.current
+        } else {
+            &mut self.enclosing[level]
+        }
+    }
+
+    //look for `id` in the functions around the one `level` deep. every function in between picks it up as an
+    //upvalue on the way through, so a closure only ever has to look one function out.
+    pub(crate) fn resolve_upvalue(&mut self, level: usize, id: &str) -> Option<u32> {
+        if level == 0 {
+            return None;
+        }
+        let upvalue = match find_local(self.compiler_at(level - 1), id) {
+            Some(local) => {
+                local.captured = true;
+                (true, local.slot)
+            }
+            None => (false, self.resolve_upvalue(level - 1, id)?),
+        };
+        let upvalues = &mut self.compiler_at(level).proto.upvalues;
+        match upvalues.iter().position(|existing| *existing == upvalue) {
+            Some(index) => Some(index as u32),
+            None => {
+                upvalues.push(upvalue);
+                Some((upvalues.len() - 1) as u32)
+            }
+        }
+    }
+
+    pub(crate) fn emit_get(&mut self, resolved: Resolved, span: Span) {
+        let op = match resolved {
+            Resolved::Local(slot) => Op::GetLocal(slot),
+            Resolved::Upvalue(index) => Op::GetUpvalue(index),
+            Resolved::Global(slot) => Op::GetGlobal(slot),
+        };
+        self.emit(op, span);
+    }
+}
+
+pub(crate) fn find_local<'a>(compiler: &'a mut FnCompiler, id: &str) -> Option<&'a mut Local> {
+    compiler
+        .scopes
+        .iter_mut()
+        .rev()
+        .find_map(|scope| scope.iter_mut().rev().find(|local| local.name == id))
+}
+
+pub(crate) fn intern(names: &mut Vec<String>, id: &str) -> u32 {
+    match names.iter().position(|name| name == id) {
+        Some(slot) => slot as u32,
+        None => {
+            names.push(id.to_string());
+            (names.len() - 1) as u32
+        }
+    }
+}
+
+//compile a whole file (or anything else that runs at the top level). the result is the value of the last statement.
+pub(crate) fn compile_program(trees: &[Node], cstate: &mut CompilerState) -> Rc<Proto> {
+    cstate.current = FnCompiler {
+        proto: Proto {
+            name: "<script>".to_string(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    compile_body(trees, cstate);
+    compile_pending(0, cstate);
+    let end = trees.last().map(|tree| tree.span).unwrap_or_default();
+    cstate.emit(Op::Return, end);
+    Rc::new(std::mem::take(&mut cstate.current).proto)
+}
+
+//run every statement for effect, except the last one, whose value is left on the stack
+pub(crate) fn compile_body(body: &[Node], cstate: &mut CompilerState) {
+    hoist_functions(body, cstate);
+    match body.split_last() {
+        Some((last, rest)) => {
+            for tree in rest {
+                compile_statement(tree, cstate);
+            }
+            compile_expression(last, cstate);
+        }
+        None => cstate.emit_constant(Value::None, Span::default()),
+    }
+}
+
+//a block gets its own scope, and no value
+pub(crate) fn compile_block(body: &[Node], cstate: &mut CompilerState) {
+    cstate.begin_scope();
+    hoist_functions(body, cstate);
+    for tree in body {
+        compile_statement(tree, cstate);
+    }
+    cstate.end_scope();
+}
+
+//compile a node for its value - leaves exactly one value on the stack
+pub(crate) fn compile_expression(tree: &Node, cstate: &mut CompilerState) {
+    let span = tree.span;
+    match &tree.ast {
+        ASTNode::None => cstate.emit_constant(Value::None, span),
+        ASTNode::Int(num) => cstate.emit_constant(Value::Int(*num), span),
+        ASTNode::Float(num) => cstate.emit_constant(Value::Float(*num), span),
+        ASTNode::Text(text) => cstate.emit_constant(Value::Text(text.clone()), span),
+        ASTNode::Interpolated(parts) => {
+            for part in parts {
+                compile_expression(part, cstate);
+            }
+            cstate.emit(Op::Concat(parts.len() as u32), span);
+        }
+        ASTNode::Formatted(p1, spec) => {
+            compile_expression(p1, cstate);
+            let formats = &mut cstate.current.proto.formats;
+            let index = match formats.iter().position(|f| f == spec) {
+                Some(index) => index,
+                None => {
+                    formats.push(*spec);
+                    formats.len() - 1
+                }
+            };
+            cstate.emit(Op::Format(index as u32), span);
+        }
+        ASTNode::Boolean(b) => cstate.emit_constant(Value::Boolean(*b), span),
+        ASTNode::Variable(id) => {
+            let resolved = cstate.resolve(id);
+            cstate.emit_get(resolved, span);
+        }
+        ASTNode::ListLiteral(items) => {
+            for item in items {
+                compile_expression(item, cstate);
+            }
+            cstate.emit(Op::MakeList(items.len() as u32), span);
+        }
+        ASTNode::Index(list, index) => {
+            compile_expression(list, cstate);
+            compile_expression(index, cstate);
+            cstate.emit(Op::Index, span);
+        }
+        ASTNode::MapLiteral(entries) => {
+            for (key, val) in entries {
+                compile_expression(key, cstate);
+                cstate.emit(Op::MapKey, span);
+                compile_expression(val, cstate);
+            }
+            cstate.emit(Op::MakeMap(entries.len() as u32), span);
+        }
+        ASTNode::Field(target, name) => {
+            compile_expression(target, cstate);
+            let name = cstate.constant_index(Value::Text(name.clone()));
+            cstate.emit(Op::GetField(name), span);
+        }
+        ASTNode::Slice(list, from, to) => {
+            compile_expression(list, cstate);
+            compile_expression(from, cstate);
+            compile_expression(to, cstate);
+            cstate.emit(Op::Slice, span);
+        }
+        ASTNode::Add(p1, p2) => compile_binary(BinOp::Add, p1, p2, span, cstate),
+        ASTNode::Sub(p1, p2) => compile_binary(BinOp::Sub, p1, p2, span, cstate),
+        ASTNode::Mul(p1, p2) => compile_binary(BinOp::Mul, p1, p2, span, cstate),
+        ASTNode::Div(p1, p2) => compile_binary(BinOp::Div, p1, p2, span, cstate),
+        ASTNode::Modulo(p1, p2) => compile_binary(BinOp::Modulo, p1, p2, span, cstate),
+        ASTNode::Pow(p1, p2) => compile_binary(BinOp::Pow, p1, p2, span, cstate),
+        ASTNode::FloorDiv(p1, p2) => compile_binary(BinOp::FloorDiv, p1, p2, span, cstate),
+        ASTNode::BitAnd(p1, p2) => compile_binary(BinOp::BitAnd, p1, p2, span, cstate),
+        ASTNode::BitOr(p1, p2) => compile_binary(BinOp::BitOr, p1, p2, span, cstate),
+        ASTNode::BitXor(p1, p2) => compile_binary(BinOp::BitXor, p1, p2, span, cstate),
+        ASTNode::ShiftLeft(p1, p2) => compile_binary(BinOp::ShiftLeft, p1, p2, span, cstate),
+        ASTNode::ShiftRight(p1, p2) => compile_binary(BinOp::ShiftRight, p1, p2, span, cstate),
+        ASTNode::EqCheck(p1, p2) => compile_binary(BinOp::EqCheck, p1, p2, span, cstate),
+        ASTNode::NeqCheck(p1, p2) => compile_binary(BinOp::NeqCheck, p1, p2, span, cstate),
+        ASTNode::GtCheck(p1, p2) => compile_binary(BinOp::GtCheck, p1, p2, span, cstate),
+        ASTNode::LtCheck(p1, p2) => compile_binary(BinOp::LtCheck, p1, p2, span, cstate),
+        ASTNode::GteCheck(p1, p2) => compile_binary(BinOp::GteCheck, p1, p2, span, cstate),
+        ASTNode::LteCheck(p1, p2) => compile_binary(BinOp::LteCheck, p1, p2, span, cstate),
+        ASTNode::Negate(p1) => {
+            compile_expression(p1, cstate);
+            cstate.emit(Op::Negate, span);
+        }
+        ASTNode::BitNot(p1) => {
+            compile_expression(p1, cstate);
+            cstate.emit(Op::BitNot, span);
+        }
+        ASTNode::And(p1, p2) | ASTNode::Or(p1, p2) => {
+            compile_expression(p1, cstate);
+            let skip = match tree.ast {
+                ASTNode::And(..) => cstate.emit(Op::JumpIfFalsyOrPop(0), span),
+                _ => cstate.emit(Op::JumpIfTruthyOrPop(0), span),
+            };
+            compile_expression(p2, cstate);
+            cstate.patch_jump(skip);
+        }
+        ASTNode::Not(p1) => {
+            compile_expression(p1, cstate);
+            cstate.emit(Op::Not, span);
+        }
+        ASTNode::FunctionCall(callee, params) => {
+            //the callee first. a name that isn't there is an undefined function rather than an undefined variable.
+            match &callee.ast {
+                ASTNode::Variable(id) => match cstate.resolve(id) {
+                    Resolved::Global(slot) => {
+                        cstate.emit(Op::GetGlobalFunction(slot), span);
+                    }
+                    resolved => cstate.emit_get(resolved, callee.span),
+                },
+                _ => compile_expression(callee, cstate),
+            }
+            for arg in params {
+                compile_expression(arg, cstate);
+            }
+            cstate.emit(
+                Op::Call {
+                    argc: params.len() as u32,
+                },
+                span,
+            );
+        }
+        ASTNode::FunctionExpr(params, body) => emit_closure("<anonymous>", params, body, span, cstate),
+        //the thing and the arguments get evaluated before the method is looked up, same as a call
+        ASTNode::MethodCall(target, name, params) => {
+            compile_expression(target, cstate);
+            for arg in params {
+                compile_expression(arg, cstate);
+            }
+            let name = cstate.constant_index(Value::Text(name.clone()));
+            let argc = params.len() as u32;
+            cstate.emit(Op::CallMethod { name, argc }, span);
+        }
+        ASTNode::StructLiteral(name, fields) => {
+            for (_, val) in fields {
+                compile_expression(val, cstate);
+            }
+            let names = fields.iter().map(|(field, _)| field.clone()).collect();
+            let index = cstate.struct_index(name, names);
+            cstate.emit(Op::MakeStruct(index), span);
+        }
+        //statements don't have a value of their own
+        ASTNode::Set(..)
+        | ASTNode::Change(..)
+        | ASTNode::AddEq(..)
+        | ASTNode::SubEq(..)
+        | ASTNode::MulEq(..)
+        | ASTNode::DivEq(..)
+        | ASTNode::ModuloEq(..)
+        | ASTNode::PowEq(..)
+        | ASTNode::FunctionDecl(..)
+        | ASTNode::StructDecl(..)
+        | ASTNode::ImplBlock(..)
+        | ASTNode::Import(..)
+        | ASTNode::ImportFrom(..)
+        | ASTNode::IfStatement(..)
+        | ASTNode::IncrementingLoop(..)
+        | ASTNode::ConditionalLoop(..)
+        | ASTNode::EachLoop(..)
+        | ASTNode::LoopBreak
+        | ASTNode::LoopContinue
+        | ASTNode::Return(_) => {
+            compile_statement(tree, cstate);
+            cstate.emit_constant(Value::None, span);
+        }
+    }
+}
+
+pub(crate) fn compile_binary(op: BinOp, p1: &Node, p2: &Node, span: Span, cstate: &mut CompilerState) {
+    compile_expression(p1, cstate);
+    compile_expression(p2, cstate);
+    cstate.emit(Op::Binary(op), span);
+}
+
+//store the top of the stack into an existing variable
+pub(crate) fn compile_assign(id: &Node, span: Span, cstate: &mut CompilerState) {
+    let idstr = match &id.ast {
+        ASTNode::Variable(idstr) => idstr,
+        _ => "",
+    };
+    let op = match cstate.resolve(idstr) {
+        Resolved::Local(slot) => Op::SetLocal(slot),
+        Resolved::Upvalue(index) => Op::SetUpvalue(index),
+        Resolved::Global(slot) => Op::SetGlobal(slot),
+    };
+    cstate.emit(op, span);
+}
+
+//x += v is x = x + v. for xs[i] += v and p.x += v, the list and index (or the thing with the field) get duplicated
+//rather than worked out twice: list, index, list, index -> list, index, xs[i] -> list, index, xs[i] + v
+pub(crate) fn compile_compound(op: BinOp, target: &Node, valexp: &Node, span: Span, cstate: &mut CompilerState) {
+    match &target.ast {
+        ASTNode::Index(list, index) => {
+            compile_expression(list, cstate);
+            compile_expression(index, cstate);
+            cstate.emit(Op::Dup(2), span);
+            cstate.emit(Op::Index, span);
+            compile_expression(valexp, cstate);
+            cstate.emit(Op::Binary(op), span);
+            cstate.emit(Op::SetIndex, span);
+        }
+        ASTNode::Field(thing, name) => {
+            compile_expression(thing, cstate);
+            cstate.emit(Op::Dup(1), span);
+            let name = cstate.constant_index(Value::Text(name.clone()));
+            cstate.emit(Op::GetField(name), span);
+            compile_expression(valexp, cstate);
+            cstate.emit(Op::Binary(op), span);
+            cstate.emit(Op::SetField(name), span);
+        }
+        _ => {
+            //the read of x gets the whole statement's span, so a missing x is reported the same as the tree walker does
+            compile_expression(&Node::new(target.ast.clone(), span), cstate);
+            compile_expression(valexp, cstate);
+            cstate.emit(Op::Binary(op), span);
+            compile_assign(target, span, cstate);
+        }
+    }
+}
+
+//compile a node for its effects - leaves the stack how it found it
+pub(crate) fn compile_statement(tree: &Node, cstate: &mut CompilerState) {
+    let span = tree.span;
+    match &tree.ast {
+        ASTNode::Set(id, valexp) => {
+            compile_expression(valexp, cstate);
+            let idstr = match &id.ast {
+                ASTNode::Variable(idstr) => idstr.as_str(),
+                _ => "",
+            };
+            let op = if cstate.current.scopes.is_empty() {
+                Op::DefineGlobal(cstate.global_slot(idstr))
+            } else {
+                Op::SetLocal(cstate.declare_local(idstr))
+            };
+            cstate.emit(op, span);
+        }
+        //xs[i] = v goes left to right - the list, then i, then v
+        ASTNode::Change(id, valexp) => match &id.ast {
+            ASTNode::Index(list, index) => {
+                compile_expression(list, cstate);
+                compile_expression(index, cstate);
+                compile_expression(valexp, cstate);
+                cstate.emit(Op::SetIndex, span);
+            }
+            ASTNode::Field(target, name) => {
+                compile_expression(target, cstate);
+                compile_expression(valexp, cstate);
+                let name = cstate.constant_index(Value::Text(name.clone()));
+                cstate.emit(Op::SetField(name), span);
+            }
+            _ => {
+                compile_expression(valexp, cstate);
+                compile_assign(id, span, cstate);
+            }
+        },
+        ASTNode::AddEq(target, valexp) => compile_compound(BinOp::Add, target, valexp, span, cstate),
+        ASTNode::SubEq(target, valexp) => compile_compound(BinOp::Sub, target, valexp, span, cstate),
+        ASTNode::MulEq(target, valexp) => compile_compound(BinOp::Mul, target, valexp, span, cstate),
+        ASTNode::DivEq(target, valexp) => compile_compound(BinOp::Div, target, valexp, span, cstate),
+        ASTNode::ModuloEq(target, valexp) => compile_compound(BinOp::Modulo, target, valexp, span, cstate),
+        ASTNode::PowEq(target, valexp) => compile_compound(BinOp::Pow, target, valexp, span, cstate),
+        //already compiled by hoist_functions, up at the top of the body it's in
+        ASTNode::FunctionDecl(..) | ASTNode::StructDecl(..) | ASTNode::ImplBlock(..) => {}
+        //the interpreter brings in imports before any of the file runs
+        ASTNode::Import(..) | ASTNode::ImportFrom(..) => {}
+        ASTNode::IfStatement(condition, ifbody, haselse, elsebody) => {
+            let skip_if = compile_condition_jump(condition, false, cstate);
+            compile_block(ifbody, cstate);
+            if *haselse {
+                let skip_else = cstate.emit(Op::Jump(0), span);
+                cstate.patch_jump(skip_if);
+                compile_block(elsebody, cstate);
+                cstate.patch_jump(skip_else);
+            } else {
+                cstate.patch_jump(skip_if);
+            }
+        }
+        //loops check their condition at the bottom, so going around again is one jump instead of two:
+        //    jump check / body: ... / check: condition, jump body if it holds
+        ASTNode::ConditionalLoop(condition, loopbody) => {
+            let check = cstate.emit(Op::Jump(0), span);
+            let body = cstate.here();
+            let jumps = compile_loop_body(loopbody, cstate);
+            cstate.patch_jump(check);
+            for jump in jumps.continues {
+                cstate.patch_jump(jump);
+            }
+            let back = compile_condition_jump(condition, true, cstate);
+            cstate.current.proto.code[back] = match cstate.current.proto.code[back] {
+                Op::CompareJump { op, when, .. } => Op::CompareJump { op, when, to: body },
+                _ => Op::JumpIfTruthy(body),
+            };
+            for jump in jumps.breaks {
+                cstate.patch_jump(jump);
+            }
+        }
+        ASTNode::IncrementingLoop(itername, lowerbound, upperbound, loopbody) => {
+            compile_expression(lowerbound, cstate);
+            compile_expression(upperbound, cstate);
+            //the iterator (and the counter behind it) live in a scope of their own wrapped around the body
+            cstate.begin_scope();
+            let var = cstate.declare_local(&variable_name(*itername.clone()));
+            let counter = cstate.declare_slot(String::new());
+            cstate.declare_slot(String::new());
+            cstate.emit(Op::RangeInit(counter), span);
+            let check = cstate.emit(Op::Jump(0), span);
+            let body = cstate.here();
+            let jumps = compile_loop_body(loopbody, cstate);
+            cstate.patch_jump(check);
+            for jump in jumps.continues {
+                cstate.patch_jump(jump);
+            }
+            //each time around gets its own copy of the iterator, as far as any closures made in the body can tell
+            let loop_scope = cstate.current.scopes.last();
+            if loop_scope.is_some_and(|scope| scope.iter().any(|local| local.captured)) {
+                cstate.emit(Op::CloseUpvalues(var), span);
+            }
+            cstate.emit(Op::RangeNext { counter, var, body }, span);
+            for jump in jumps.breaks {
+                cstate.patch_jump(jump);
+            }
+            cstate.end_scope();
+        }
+        //laid out just like the incrementing loop, with the list's copy and a position in place of the counter.
+        //the second variable always gets the slot right after the first, named or not, since EachNext fills both.
+        ASTNode::EachLoop(itername, valname, list, loopbody) => {
+            compile_expression(list, cstate);
+            cstate.begin_scope();
+            let var = cstate.declare_local(&variable_name(*itername.clone()));
+            let valname = valname.as_ref().map(|valname| variable_name(*valname.clone()));
+            let pair = valname.is_some();
+            cstate.declare_slot(valname.unwrap_or_default());
+            let items = cstate.declare_slot(String::new());
+            cstate.declare_slot(String::new());
+            cstate.emit(Op::EachInit(items), span);
+            let check = cstate.emit(Op::Jump(0), span);
+            let body = cstate.here();
+            let jumps = compile_loop_body(loopbody, cstate);
+            cstate.patch_jump(check);
+            for jump in jumps.continues {
+                cstate.patch_jump(jump);
+            }
+            let loop_scope = cstate.current.scopes.last();
+            if loop_scope.is_some_and(|scope| scope.iter().any(|local| local.captured)) {
+                cstate.emit(Op::CloseUpvalues(var), span);
+            }
+            cstate.emit(Op::EachNext { items, var, pair, body }, span);
+            for jump in jumps.breaks {
+                cstate.patch_jump(jump);
+            }
+            cstate.end_scope();
+        }
+        ASTNode::LoopBreak => {
+            close_loop_scopes(span, cstate);
+            let jump = cstate.emit(Op::Jump(0), span);
+            if let Some(jumps) = cstate.current.loops.last_mut() {
+                jumps.breaks.push(jump);
+            }
+        }
+        ASTNode::LoopContinue => {
+            close_loop_scopes(span, cstate);
+            let jump = cstate.emit(Op::Jump(0), span);
+            if let Some(jumps) = cstate.current.loops.last_mut() {
+                jumps.continues.push(jump);
+            }
+        }
+        ASTNode::Return(valexp) => {
+            compile_expression(valexp, cstate);
+            cstate.emit(Op::Return, span);
+        }
+        _ => {
+            compile_expression(tree, cstate);
+            cstate.emit(Op::Pop, span);
+        }
+    }
+}
+
+//make the functions a body declares up front, so they exist from the moment it starts running and can be called
+//before (or from inside of each other ahead of) where they're written. same goes for its structs and their methods.
+pub(crate) fn hoist_functions(body: &[Node], cstate: &mut CompilerState) {
+    for tree in body {
+        if let ASTNode::StructDecl(name, fields) = &tree.ast {
+            let index = cstate.struct_index(name, fields.clone());
+            cstate.emit(Op::DefineStruct(index), tree.span);
+        }
+    }
+    for tree in body {
+        match &tree.ast {
+            ASTNode::FunctionDecl(id, params, fbody, _) => {
+                let idstr = function_name(id);
+                let target = if cstate.current.scopes.is_empty() {
+                    Op::DefineGlobal(cstate.global_slot(idstr))
+                } else {
+                    Op::SetLocal(cstate.declare_local(idstr))
+                };
+                emit_closure(idstr, params, fbody, tree.span, cstate);
+                cstate.emit(target, tree.span);
+            }
+            ASTNode::ImplBlock(ty, methods) => {
+                for method in methods {
+                    if let ASTNode::FunctionDecl(id, params, fbody, _) = &method.ast {
+                        let qualified = format!("{}.{}", ty, function_name(id));
+                        emit_closure(&qualified, params, fbody, method.span, cstate);
+                        let ty = cstate.constant_index(Value::Text(cstate.struct_key(ty)));
+                        let name = cstate.constant_index(Value::Text(function_name(id).to_string()));
+                        cstate.emit(Op::DefineMethod { ty, name }, tree.span);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+//leave a closure over a function on the stack. its body gets compiled once the scope we're in is over.
+pub(crate) fn emit_closure(name: &str, params: &[Node], body: &[Node], span: Span, cstate: &mut CompilerState) {
+    let protos = &mut cstate.current.proto.protos;
+    protos.push(Rc::default());
+    let proto = (protos.len() - 1) as u32;
+    cstate.current.pending.push(PendingFunction {
+        proto,
+        name: name.to_string(),
+        params: params.to_vec(),
+        body: body.to_vec(),
+        span,
+        depth: cstate.current.scopes.len(),
+    });
+    cstate.emit(Op::Closure(proto), span);
+}
+
+//compile the bodies of the functions made at `depth` scopes in or deeper
+pub(crate) fn compile_pending(depth: usize, cstate: &mut CompilerState) {
+    while let Some(pending) = cstate.current.pending.pop_if(|pending| pending.depth >= depth) {
+        let compiled = compile_function(&pending, cstate);
+        cstate.current.proto.protos[pending.proto as usize] = Rc::new(compiled);
+    }
+}
+
+//compile a function's body on its own, with only its params (and whatever it can reach out for) for company
+pub(crate) fn compile_function(pending: &PendingFunction, cstate: &mut CompilerState) -> Proto {
+    let params: Vec<Local> = pending
+        .params
+        .iter()
+        .enumerate()
+        .map(|(i, p)| Local {
+            name: variable_name(p.clone()),
+            slot: i as u32,
+            captured: false,
+        })
+        .collect();
+    let outer = std::mem::replace(
+        &mut cstate.current,
+        FnCompiler {
+            proto: Proto {
+                name: pending.name.clone(),
+                params: params.iter().map(|param| param.name.clone()).collect(),
+                locals: params.len(),
+                ..Default::default()
+            },
+            nextSlot: params.len() as u32,
+            scopes: vec![params],
+            ..Default::default()
+        },
+    );
+    cstate.enclosing.push(outer);
+    cstate.begin_scope();
+    compile_body(&pending.body, cstate);
+    cstate.end_scope();
+    compile_pending(0, cstate);
+    cstate.emit(Op::Return, pending.span);
+    let outer = cstate.enclosing.pop().unwrap_or_default();
+    std::mem::replace(&mut cstate.current, outer).proto
+}
+
+//compile a loop body, handing back its breaks and continues for the caller to point somewhere
+pub(crate) fn compile_loop_body(loopbody: &[Node], cstate: &mut CompilerState) -> LoopJumps {
+    cstate.current.loops.push(LoopJumps {
+        firstSlot: cstate.current.nextSlot,
+        ..Default::default()
+    });
+    compile_block(loopbody, cstate);
+    cstate.current.loops.pop().unwrap_or_default()
+}
+
+//a break or continue is leaving the loop body's scopes without going through their ends. whether anything in them
+//got captured isn't known until the closures' bodies are compiled, so they always get closed.
+pub(crate) fn close_loop_scopes(span: Span, cstate: &mut CompilerState) {
+    if let Some(first) = cstate.current.loops.last().map(|jumps| jumps.firstSlot) {
+        cstate.emit(Op::CloseUpvalues(first), span);
+    }
+}
+
+//a condition, then a jump (left for the caller to patch) that's taken when the condition comes out as `when`.
+//if wants to skip ahead when it's false, loops want to go back around when it's true.
+pub(crate) fn compile_condition_jump(condition: &Node, when: bool, cstate: &mut CompilerState) -> usize {
+    let comparison = match &condition.ast {
+        ASTNode::EqCheck(p1, p2) => Some((BinOp::EqCheck, p1, p2)),
+        ASTNode::NeqCheck(p1, p2) => Some((BinOp::NeqCheck, p1, p2)),
+        ASTNode::GtCheck(p1, p2) => Some((BinOp::GtCheck, p1, p2)),
+        ASTNode::LtCheck(p1, p2) => Some((BinOp::LtCheck, p1, p2)),
+        ASTNode::GteCheck(p1, p2) => Some((BinOp::GteCheck, p1, p2)),
+        ASTNode::LteCheck(p1, p2) => Some((BinOp::LteCheck, p1, p2)),
+        _ => None,
+    };
+    if let Some((op, p1, p2)) = comparison {
+        compile_expression(p1, cstate);
+        compile_expression(p2, cstate);
+        return cstate.emit(Op::CompareJump { op, when, to: 0 }, condition.span);
+    }
+    compile_expression(condition, cstate);
+    let op = if when { Op::JumpIfTruthy(0) } else { Op::JumpIfFalsy(0) };
+    cstate.emit(op, condition.span)
+}
+
+//a listing of the bytecode, for debug output
+pub(crate) fn disassemble(proto: &Proto) -> String {
+    let mut out = format!(
+        "== {} ({} params, {} locals) ==\n",
+        proto.name,
+        proto.params.len(),
+        proto.locals
+    );
+    for (i, op) in proto.code.iter().enumerate() {
+        out += &format!("{:04} {:?}", i, op);
+        if let Op::Constant(index) = op {
+            out += &format!(" ({:?})", proto.constants[*index as usize]);
+        }
+        out += "\n";
+    }
+    for inner in &proto.protos {
+        out += &disassemble(inner);
+    }
+    out
+}
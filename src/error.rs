@@ -0,0 +1,206 @@
+use crate::ast::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    UnexpectedToken { expected: String, found: String },
+    UnexpectedEof { expected: String },
+    //the lexer didn't know what to make of this
+    UnexpectedCharacter(String),
+    //these point at the opening brace/paren
+    UnclosedBrace,
+    UnclosedParen,
+    UnclosedBracket,
+    //let x 5;
+    MissingAssign,
+    //5 = x; or xs[1..2] = x;
+    InvalidAssignTarget,
+    //break/continue/return (or an import) somewhere they don't mean anything. holds the keyword.
+    Misplaced(String),
+    InvalidNumber(String),
+    //the same field twice in a struct, or in a struct literal
+    DuplicateField(String),
+    //points at the opening quote
+    UnterminatedString,
+    //points at the `{`
+    UnterminatedInterpolation,
+    //points at the /* that never gets its */
+    UnterminatedComment,
+    //holds the escape as written, backslash and all
+    InvalidEscape(String),
+    //whatever came after the colon in "{x:...}"
+    InvalidFormatSpec(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, span: Span) -> ParseError {
+        ParseError { kind, span }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnexpectedToken { expected, found } => {
+                write!(f, "expected {}, found `{}`", expected, found)
+            }
+            ParseErrorKind::UnexpectedEof { expected } => {
+                write!(f, "expected {}, found the end of the file", expected)
+            }
+            ParseErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character `{}`", c),
+            ParseErrorKind::UnclosedBrace => write!(f, "this `{{` is never closed"),
+            ParseErrorKind::UnclosedParen => write!(f, "this `(` is never closed"),
+            ParseErrorKind::UnclosedBracket => write!(f, "this `[` is never closed"),
+            ParseErrorKind::MissingAssign => write!(f, "expected `=` after the variable name in `let`"),
+            ParseErrorKind::InvalidAssignTarget => write!(f, "can only assign to a variable, an element or a field"),
+            ParseErrorKind::Misplaced(kw) if kw == "return" => {
+                write!(f, "`return` outside of a function")
+            }
+            ParseErrorKind::Misplaced(kw) if kw == "import" || kw == "from" => {
+                write!(f, "`{}` can only go at the top level of a file", kw)
+            }
+            ParseErrorKind::Misplaced(kw) => write!(f, "`{}` outside of a loop", kw),
+            ParseErrorKind::InvalidNumber(num) => write!(f, "`{}` isn't a valid number", num),
+            ParseErrorKind::DuplicateField(field) => write!(f, "field `{}` is given more than once", field),
+            ParseErrorKind::UnterminatedString => write!(f, "this string is never closed"),
+            ParseErrorKind::UnterminatedInterpolation => write!(f, "this interpolation is never closed"),
+            ParseErrorKind::UnterminatedComment => write!(f, "this comment is never closed"),
+            ParseErrorKind::InvalidEscape(escape) => write!(f, "`{}` isn't a valid escape", escape),
+            ParseErrorKind::InvalidFormatSpec(spec) => write!(f, "`{}` isn't a valid format spec", spec),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    UndefinedVariable(String),
+    UndefinedFunction(String),
+    ArityMismatch {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    TypeError(String),
+    //indexes as written, so negative ones stay negative
+    IndexOutOfBounds {
+        index: i64,
+        length: usize,
+    },
+    //reading a key a map doesn't have
+    MissingKey(String),
+    UndefinedStruct(String),
+    //a field the struct doesn't declare
+    NoSuchField {
+        ty: String,
+        field: String,
+    },
+    //a struct literal that leaves one of the fields out
+    MissingField {
+        ty: String,
+        field: String,
+    },
+    NoSuchMethod {
+        ty: String,
+        method: String,
+    },
+    DivisionByZero,
+    //an int operation whose result doesn't fit in an int. holds the operator.
+    IntegerOverflow(String),
+    //too many calls deep, almost certainly runaway recursion
+    StackOverflow,
+    //whatever a native function wanted to complain about
+    Native(String),
+    //an import whose file isn't next to the importing one, or anywhere on SK_PATH. holds the path as written.
+    ModuleNotFound(String),
+    //a module's file that's there but couldn't be read - not text, say. holds the path as written.
+    ModuleUnreadable {
+        path: String,
+        err: String,
+    },
+    //the files going round, starting and ending with the same one
+    ImportCycle(Vec<String>),
+    //something a module's top level never defined
+    NoSuchExport {
+        module: String,
+        name: String,
+    },
+}
+
+//how many script function calls can be in progress at once
+pub(crate) const MAX_CALL_DEPTH: usize = 1000;
+
+//one script-level function call that was in progress when something went wrong
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    pub function: String,
+    //where it got called from
+    pub call_site: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Span,
+    //outermost call first
+    pub stack: Vec<StackFrame>,
+}
+
+impl std::fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeErrorKind::UndefinedVariable(id) => write!(f, "undefined variable `{}`", id),
+            RuntimeErrorKind::UndefinedFunction(id) => write!(f, "undefined function `{}`", id),
+            RuntimeErrorKind::ArityMismatch {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` takes {} argument(s) but was given {}",
+                function, expected, found
+            ),
+            RuntimeErrorKind::TypeError(msg) => write!(f, "type error: {}", msg),
+            RuntimeErrorKind::IndexOutOfBounds { index, length } => {
+                write!(f, "index {} is out of bounds for a list of length {}", index, length)
+            }
+            RuntimeErrorKind::MissingKey(key) => write!(f, "no key {:?} in the map", key),
+            RuntimeErrorKind::UndefinedStruct(id) => write!(f, "undefined struct `{}`", id),
+            RuntimeErrorKind::NoSuchField { ty, field } => write!(f, "`{}` has no field `{}`", ty, field),
+            RuntimeErrorKind::MissingField { ty, field } => write!(f, "missing field `{}` for a `{}`", field, ty),
+            RuntimeErrorKind::NoSuchMethod { ty, method } => write!(f, "`{}` has no method `{}`", ty, method),
+            RuntimeErrorKind::DivisionByZero => write!(f, "division by zero"),
+            RuntimeErrorKind::IntegerOverflow(op) => write!(f, "integer overflow in `{}`", op),
+            RuntimeErrorKind::StackOverflow => {
+                write!(f, "stack overflow: more than {} calls deep", MAX_CALL_DEPTH)
+            }
+            RuntimeErrorKind::Native(msg) => write!(f, "{}", msg),
+            RuntimeErrorKind::ModuleNotFound(path) => write!(f, "couldn't find module `{}`", path),
+            RuntimeErrorKind::ModuleUnreadable { path, err } => write!(f, "couldn't read module `{}`: {}", path, err),
+            RuntimeErrorKind::ImportCycle(files) => write!(f, "import cycle: {}", files.join(" -> ")),
+            RuntimeErrorKind::NoSuchExport { module, name } => write!(f, "module `{}` has no `{}`", module, name),
+        }
+    }
+}
+
+impl RuntimeError {
+    /// An error that doesn't point anywhere yet, for native functions to hand back.
+    /// The span and stack get filled in with the call's.
+    pub fn new(kind: RuntimeErrorKind) -> RuntimeError {
+        RuntimeError {
+            kind,
+            span: Span::default(),
+            stack: vec![],
+        }
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
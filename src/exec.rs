@@ -0,0 +1,612 @@
+use crate::ast::{function_name, variable_name, ASTNode, Node, Span};
+use crate::builtins::call_native;
+use crate::error::{RuntimeError, RuntimeErrorKind, StackFrame, MAX_CALL_DEPTH};
+use crate::value::{
+    binary_op, bit_not_value, declare_struct, define_method, find_method, format_value, get_field, index_value,
+    int_operands, is_truthy, loop_copy, loop_entry, make_instance, map_key, negate_value, not_value, set_field,
+    set_index, slice_value, struct_key, BinOp, Callable, Function, StructType, Value,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+//a script function, as declared, along with the scope it was declared in. its body's variables get looked up
+//from there, which is what lets it keep using them after that scope is done.
+pub(crate) struct ScriptFunction {
+    pub(crate) name: String,
+    pub(crate) params: Vec<String>,
+    pub(crate) body: Vec<Node>,
+    pub(crate) env: Rc<RefCell<Scope>>,
+    //the module it was declared in, whose structs its body means
+    pub(crate) module: Option<String>,
+}
+
+//one level of variables. reads that miss here carry on out to the parent.
+#[derive(Debug, Default)]
+pub(crate) struct Scope {
+    pub(crate) vars: HashMap<String, Value>,
+    pub(crate) parent: Option<Rc<RefCell<Scope>>>,
+}
+
+impl Scope {
+    pub(crate) fn child_of(parent: &Rc<RefCell<Scope>>) -> Rc<RefCell<Scope>> {
+        Rc::new(RefCell::new(Scope {
+            vars: HashMap::new(),
+            parent: Some(parent.clone()),
+        }))
+    }
+
+    pub(crate) fn lookup(&self, id: &str) -> Option<Value> {
+        match self.vars.get(id) {
+            Some(val) => Some(val.clone()),
+            None => self.parent.as_ref().and_then(|p| p.borrow().lookup(id)),
+        }
+    }
+
+    //overwrite the nearest binding of id. returns false if there isn't one anywhere.
+    pub(crate) fn assign(&mut self, id: &str, val: Value) -> bool {
+        if let Some(slot) = self.vars.get_mut(id) {
+            *slot = val;
+            return true;
+        }
+        match &self.parent {
+            Some(p) => p.borrow_mut().assign(id, val),
+            None => false,
+        }
+    }
+}
+
+//a break/continue/return that's on its way out. blocks stop running once one of these is set,
+//and whichever loop or call it was meant for picks it up and sets things back to Normal.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+#[derive(Debug)]
+pub(crate) struct ExecutionContext {
+    //the innermost scope we're running in
+    pub(crate) scope: Rc<RefCell<Scope>>,
+    //the file-level scope, which is what function bodies hang off of
+    pub(crate) globals: Rc<RefCell<Scope>>,
+    //the script functions we're currently inside of, for tracebacks
+    pub(crate) callStack: Vec<StackFrame>,
+    pub(crate) flow: Flow,
+    //every struct declared so far. unlike variables, these aren't scoped - a struct is known everywhere once declared.
+    pub(crate) structs: HashMap<String, Rc<StructType>>,
+    //the path of the module whose code is running, if it's a module's
+    pub(crate) module: Option<String>,
+}
+
+impl ExecutionContext {
+    pub(crate) fn new() -> ExecutionContext {
+        let globals = Rc::new(RefCell::new(Scope::default()));
+        ExecutionContext {
+            scope: globals.clone(),
+            globals,
+            callStack: vec![],
+            flow: Flow::Normal,
+            structs: HashMap::new(),
+            module: None,
+        }
+    }
+
+    //build an error that remembers which calls we were in when it happened
+    pub(crate) fn error(&self, kind: RuntimeErrorKind, span: Span) -> RuntimeError {
+        RuntimeError {
+            kind,
+            span,
+            stack: self.callStack.clone(),
+        }
+    }
+
+    //let - always lands in the current scope, shadowing anything further out
+    pub(crate) fn define(&mut self, id: String, val: Value) {
+        self.scope.borrow_mut().vars.insert(id, val);
+    }
+
+    pub(crate) fn lookup(&self, id: &str, span: Span) -> Result<Value, RuntimeError> {
+        let found = self.scope.borrow().lookup(id);
+        found.ok_or_else(|| self.error(RuntimeErrorKind::UndefinedVariable(id.to_string()), span))
+    }
+
+    //x = v - changes whichever x is closest
+    pub(crate) fn assign(&mut self, id: &str, val: Value, span: Span) -> Result<(), RuntimeError> {
+        if self.scope.borrow_mut().assign(id, val) {
+            Ok(())
+        } else {
+            Err(self.error(RuntimeErrorKind::UndefinedVariable(id.to_string()), span))
+        }
+    }
+
+    //the functions a body declares exist from the moment it starts running, so they can be called before
+    //(or from inside of each other ahead of) where they're written. same goes for its structs and their methods.
+    pub(crate) fn hoist_functions(&mut self, body: &[Node]) -> Result<(), RuntimeError> {
+        for tree in body {
+            if let ASTNode::StructDecl(name, fields) = &tree.ast {
+                let key = self.struct_key(name);
+                declare_struct(&mut self.structs, &key, fields);
+            }
+        }
+        for tree in body {
+            match &tree.ast {
+                ASTNode::FunctionDecl(id, params, fbody, _) => {
+                    let val = self.make_function(function_name(id).to_string(), params, fbody);
+                    self.define(function_name(id).to_string(), val);
+                }
+                ASTNode::ImplBlock(ty, methods) => {
+                    for method in methods {
+                        if let ASTNode::FunctionDecl(id, params, fbody, _) = &method.ast {
+                            let val = self.make_function(format!("{}.{}", ty, function_name(id)), params, fbody);
+                            define_method(&self.structs, &self.struct_key(ty), function_name(id), val)
+                                .map_err(|kind| self.error(kind, tree.span))?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    //a function declared right here, hanging off of the scope we're in
+    pub(crate) fn make_function(&self, name: String, params: &[Node], body: &[Node]) -> Value {
+        let function = ScriptFunction {
+            name,
+            params: params.iter().cloned().map(variable_name).collect(),
+            body: body.to_vec(),
+            env: self.scope.clone(),
+            module: self.module.clone(),
+        };
+        Value::Function(Function::new(Callable::Tree(function)))
+    }
+
+    //what the struct `name` is filed under in the code running right now
+    pub(crate) fn struct_key(&self, name: &str) -> String {
+        struct_key(self.module.as_deref(), name)
+    }
+
+    //run the trees in a fresh child of `parent`, then put our old scope back no matter how it went
+    pub(crate) fn exec_in_scope(
+        &mut self,
+        body: Vec<Node>,
+        parent: Rc<RefCell<Scope>>,
+    ) -> Result<Value, RuntimeError> {
+        let outer = std::mem::replace(&mut self.scope, Scope::child_of(&parent));
+        let mut ret_val = self.hoist_functions(&body).map(|_| Value::None);
+        if ret_val.is_ok() {
+            for tree in body {
+                ret_val = exec(tree, self);
+                if ret_val.is_err() || self.flow != Flow::Normal {
+                    break;
+                }
+            }
+        }
+        self.scope = outer;
+        ret_val
+    }
+
+    //called after a loop body runs - eats a break/continue meant for this loop, and says whether to keep looping.
+    //a return has to keep going out to the function, so it stops the loop without being eaten.
+    pub(crate) fn continue_loop(&mut self) -> bool {
+        match self.flow {
+            Flow::Normal => true,
+            Flow::Continue => {
+                self.flow = Flow::Normal;
+                true
+            }
+            Flow::Break => {
+                self.flow = Flow::Normal;
+                false
+            }
+            Flow::Return(_) => false,
+        }
+    }
+
+    //the body hangs off the scope the function was declared in rather than off of us, so callees can't see the
+    //caller's locals. calls from the host don't have a call site, and don't show up in tracebacks.
+    pub(crate) fn call(
+        &mut self,
+        function: &Function,
+        args: Vec<Value>,
+        call_site: Option<Span>,
+    ) -> Result<Value, RuntimeError> {
+        let span = call_site.unwrap_or_default();
+        if function.arity() != args.len() {
+            return Err(self.error(
+                RuntimeErrorKind::ArityMismatch {
+                    function: function.name().to_string(),
+                    expected: function.arity(),
+                    found: args.len(),
+                },
+                span,
+            ));
+        }
+        let function = match &*function.0 {
+            Callable::Tree(function) => function,
+            Callable::Native(native) => return call_native(native, &args).map_err(|kind| self.error(kind, span)),
+            Callable::Compiled(_) => {
+                return Err(self.error(
+                    RuntimeErrorKind::TypeError("cannot call a function from a different interpreter".to_string()),
+                    span,
+                ))
+            }
+        };
+        if self.callStack.len() >= MAX_CALL_DEPTH {
+            return Err(self.error(RuntimeErrorKind::StackOverflow, span));
+        }
+        //populate the parameters
+        let f_scope = Scope::child_of(&function.env);
+        for (param, val) in function.params.iter().zip(args) {
+            f_scope.borrow_mut().vars.insert(param.clone(), val);
+        }
+        //next, run the function execution - we return the result of the last function call (a rather rust-like convention honestly)
+        if let Some(call_site) = call_site {
+            self.callStack.push(StackFrame {
+                function: function.name.clone(),
+                call_site,
+            });
+        }
+        let module = std::mem::replace(&mut self.module, function.module.clone());
+        let ret_val = self.exec_in_scope(function.body.clone(), f_scope);
+        self.module = module;
+        if call_site.is_some() {
+            self.callStack.pop();
+        }
+        //an explicit return beats whatever the last statement was
+        match std::mem::replace(&mut self.flow, Flow::Normal) {
+            Flow::Return(val) => Ok(val),
+            _ => ret_val,
+        }
+    }
+
+    //a block gets its own scope nested inside wherever we are right now
+    pub(crate) fn exec_block(&mut self, body: Vec<Node>) -> Result<Value, RuntimeError> {
+        let parent = self.scope.clone();
+        self.exec_in_scope(body, parent)
+    }
+}
+
+//evaluate both sides of an operator and apply it
+pub(crate) fn exec_binary(
+    op: BinOp,
+    p1: Node,
+    p2: Node,
+    span: Span,
+    executionContext: &mut ExecutionContext,
+) -> Result<Value, RuntimeError> {
+    //actually get ourselves some values
+    let v1 = exec(p1, executionContext)?;
+    let v2 = exec(p2, executionContext)?;
+    binary_op(op, v1, v2).map_err(|kind| executionContext.error(kind, span))
+}
+
+//evaluate both sides of an ints-only operator
+pub(crate) fn exec_int_operands(
+    p1: Node,
+    p2: Node,
+    op: &str,
+    span: Span,
+    executionContext: &mut ExecutionContext,
+) -> Result<(i64, i64), RuntimeError> {
+    let v1 = exec(p1, executionContext)?;
+    let v2 = exec(p2, executionContext)?;
+    int_operands(op, &v1, &v2).map_err(|kind| executionContext.error(kind, span))
+}
+
+//x += v works just like x = x + v: x gets read (so it has to be there already) before v does anything.
+//for xs[i] += v and p.x += v, the list and index (or the thing with the field) only get worked out once.
+pub(crate) fn exec_compound(
+    op: BinOp,
+    target: Node,
+    valexp: Node,
+    span: Span,
+    executionContext: &mut ExecutionContext,
+) -> Result<Value, RuntimeError> {
+    match target.ast {
+        ASTNode::Index(list, index) => {
+            let list = exec(*list, executionContext)?;
+            let index = exec(*index, executionContext)?;
+            let current = index_value(&list, &index).map_err(|kind| executionContext.error(kind, span))?;
+            let val = exec(valexp, executionContext)?;
+            binary_op(op, current, val)
+                .and_then(|result| set_index(&list, &index, result))
+                .map_err(|kind| executionContext.error(kind, span))?;
+        }
+        ASTNode::Field(target, name) => {
+            let target = exec(*target, executionContext)?;
+            let current = get_field(&target, &name).map_err(|kind| executionContext.error(kind, span))?;
+            let val = exec(valexp, executionContext)?;
+            binary_op(op, current, val)
+                .and_then(|result| set_field(&target, &name, result))
+                .map_err(|kind| executionContext.error(kind, span))?;
+        }
+        _ => {
+            let idstr = variable_name(target);
+            let current = executionContext.lookup(&idstr, span)?;
+            let val = exec(valexp, executionContext)?;
+            let result = binary_op(op, current, val).map_err(|kind| executionContext.error(kind, span))?;
+            executionContext.assign(&idstr, result, span)?;
+        }
+    }
+    Ok(Value::None)
+}
+
+pub(crate) fn exec(tree: Node, executionContext: &mut ExecutionContext) -> Result<Value, RuntimeError> {
+    let span = tree.span;
+    match tree.ast {
+        ASTNode::Set(id, valexp) => {
+            let val = exec(*valexp, executionContext)?;
+            executionContext.define(variable_name(*id), val);
+            //nothing should be relying on a var decl for a value unless your code has serious issues.
+            Ok(Value::None)
+        }
+        //xs[i] = v goes left to right - the list, then i, then v
+        ASTNode::Change(id, valexp) => match id.ast {
+            ASTNode::Index(list, index) => {
+                let list = exec(*list, executionContext)?;
+                let index = exec(*index, executionContext)?;
+                let val = exec(*valexp, executionContext)?;
+                set_index(&list, &index, val).map_err(|kind| executionContext.error(kind, span))?;
+                Ok(Value::None)
+            }
+            ASTNode::Field(target, name) => {
+                let target = exec(*target, executionContext)?;
+                let val = exec(*valexp, executionContext)?;
+                set_field(&target, &name, val).map_err(|kind| executionContext.error(kind, span))?;
+                Ok(Value::None)
+            }
+            _ => {
+                let val = exec(*valexp, executionContext)?;
+                executionContext.assign(&variable_name(*id), val, span)?;
+                Ok(Value::None)
+            }
+        },
+        ASTNode::AddEq(target, valexp) => exec_compound(BinOp::Add, *target, *valexp, span, executionContext),
+        ASTNode::SubEq(target, valexp) => exec_compound(BinOp::Sub, *target, *valexp, span, executionContext),
+        ASTNode::MulEq(target, valexp) => exec_compound(BinOp::Mul, *target, *valexp, span, executionContext),
+        ASTNode::DivEq(target, valexp) => exec_compound(BinOp::Div, *target, *valexp, span, executionContext),
+        ASTNode::ModuloEq(target, valexp) => exec_compound(BinOp::Modulo, *target, *valexp, span, executionContext),
+        ASTNode::PowEq(target, valexp) => exec_compound(BinOp::Pow, *target, *valexp, span, executionContext),
+        ASTNode::Add(p1, p2) => exec_binary(BinOp::Add, *p1, *p2, span, executionContext),
+        ASTNode::Sub(p1, p2) => exec_binary(BinOp::Sub, *p1, *p2, span, executionContext),
+        ASTNode::Mul(p1, p2) => exec_binary(BinOp::Mul, *p1, *p2, span, executionContext),
+        ASTNode::Div(p1, p2) => exec_binary(BinOp::Div, *p1, *p2, span, executionContext),
+        ASTNode::Modulo(p1, p2) => exec_binary(BinOp::Modulo, *p1, *p2, span, executionContext),
+        ASTNode::Pow(p1, p2) => exec_binary(BinOp::Pow, *p1, *p2, span, executionContext),
+        ASTNode::FloorDiv(p1, p2) => exec_binary(BinOp::FloorDiv, *p1, *p2, span, executionContext),
+        ASTNode::BitAnd(p1, p2) => exec_binary(BinOp::BitAnd, *p1, *p2, span, executionContext),
+        ASTNode::BitOr(p1, p2) => exec_binary(BinOp::BitOr, *p1, *p2, span, executionContext),
+        ASTNode::BitXor(p1, p2) => exec_binary(BinOp::BitXor, *p1, *p2, span, executionContext),
+        ASTNode::ShiftLeft(p1, p2) => exec_binary(BinOp::ShiftLeft, *p1, *p2, span, executionContext),
+        ASTNode::ShiftRight(p1, p2) => exec_binary(BinOp::ShiftRight, *p1, *p2, span, executionContext),
+        ASTNode::EqCheck(p1, p2) => exec_binary(BinOp::EqCheck, *p1, *p2, span, executionContext),
+        ASTNode::NeqCheck(p1, p2) => exec_binary(BinOp::NeqCheck, *p1, *p2, span, executionContext),
+        ASTNode::GtCheck(p1, p2) => exec_binary(BinOp::GtCheck, *p1, *p2, span, executionContext),
+        ASTNode::LtCheck(p1, p2) => exec_binary(BinOp::LtCheck, *p1, *p2, span, executionContext),
+        ASTNode::GteCheck(p1, p2) => exec_binary(BinOp::GteCheck, *p1, *p2, span, executionContext),
+        ASTNode::LteCheck(p1, p2) => exec_binary(BinOp::LteCheck, *p1, *p2, span, executionContext),
+        ASTNode::Negate(p1) => {
+            let val = exec(*p1, executionContext)?;
+            negate_value(val).map_err(|kind| executionContext.error(kind, span))
+        }
+        ASTNode::Not(p1) => Ok(not_value(&exec(*p1, executionContext)?)),
+        ASTNode::BitNot(p1) => {
+            let val = exec(*p1, executionContext)?;
+            bit_not_value(val).map_err(|kind| executionContext.error(kind, span))
+        }
+        ASTNode::And(p1, p2) => {
+            let lhs = exec(*p1, executionContext)?;
+            if is_truthy(&lhs) {
+                exec(*p2, executionContext)
+            } else {
+                Ok(lhs)
+            }
+        }
+        ASTNode::Or(p1, p2) => {
+            let lhs = exec(*p1, executionContext)?;
+            if is_truthy(&lhs) {
+                Ok(lhs)
+            } else {
+                exec(*p2, executionContext)
+            }
+        }
+        //already defined by hoist_functions, back when the body it's in started running
+        ASTNode::FunctionDecl(..) | ASTNode::StructDecl(..) | ASTNode::ImplBlock(..) => Ok(Value::None),
+        //and imports by the interpreter, before the file started running at all
+        ASTNode::Import(..) | ASTNode::ImportFrom(..) => Ok(Value::None),
+        ASTNode::FunctionCall(callee, params) => {
+            //the callee first. a name that isn't there is an undefined function rather than an undefined variable.
+            let callee = match callee.ast {
+                ASTNode::Variable(id) => {
+                    let found = executionContext.scope.borrow().lookup(&id);
+                    match found {
+                        Some(val) => val,
+                        None => return Err(executionContext.error(RuntimeErrorKind::UndefinedFunction(id), span)),
+                    }
+                }
+                _ => exec(*callee, executionContext)?,
+            };
+            //the arguments get evaluated out here, in the caller's scope, before we even look at what we're calling
+            let mut args = Vec::with_capacity(params.len());
+            for arg in params {
+                args.push(exec(arg, executionContext)?);
+            }
+            match callee {
+                Value::Function(function) => executionContext.call(&function, args, Some(span)),
+                val => Err(executionContext.error(
+                    RuntimeErrorKind::TypeError(format!("cannot call {}", val.type_name())),
+                    span,
+                )),
+            }
+        }
+        ASTNode::FunctionExpr(params, body) => {
+            Ok(executionContext.make_function("<anonymous>".to_string(), &params, &body))
+        }
+        //the thing and the arguments get evaluated before the method is looked up, same as a call
+        ASTNode::MethodCall(target, name, params) => {
+            let target = exec(*target, executionContext)?;
+            let mut args = Vec::with_capacity(params.len() + 1);
+            for arg in params {
+                args.push(exec(arg, executionContext)?);
+            }
+            let (callee, takes_self) =
+                find_method(&target, &name).map_err(|kind| executionContext.error(kind, span))?;
+            if takes_self {
+                args.insert(0, target);
+            }
+            match callee {
+                Value::Function(function) => executionContext.call(&function, args, Some(span)),
+                val => Err(executionContext.error(
+                    RuntimeErrorKind::TypeError(format!("cannot call {}", val.type_name())),
+                    span,
+                )),
+            }
+        }
+        ASTNode::Variable(id) => executionContext.lookup(&id, span),
+        ASTNode::ListLiteral(items) => {
+            let mut list = Vec::with_capacity(items.len());
+            for item in items {
+                list.push(exec(item, executionContext)?);
+            }
+            Ok(Value::list(list))
+        }
+        ASTNode::Index(list, index) => {
+            let list = exec(*list, executionContext)?;
+            let index = exec(*index, executionContext)?;
+            index_value(&list, &index).map_err(|kind| executionContext.error(kind, span))
+        }
+        //keys and values both get evaluated in the order they're written
+        ASTNode::MapLiteral(entries) => {
+            let mut map = Vec::with_capacity(entries.len());
+            for (key, val) in entries {
+                let key = exec(key, executionContext)?;
+                let key = map_key(&key).map_err(|kind| executionContext.error(kind, span))?;
+                map.push((key.to_string(), exec(val, executionContext)?));
+            }
+            Ok(Value::map(map))
+        }
+        ASTNode::Field(target, name) => {
+            let target = exec(*target, executionContext)?;
+            get_field(&target, &name).map_err(|kind| executionContext.error(kind, span))
+        }
+        ASTNode::StructLiteral(name, fields) => {
+            let (names, vals): (Vec<String>, Vec<Node>) = fields.into_iter().unzip();
+            let mut args = Vec::with_capacity(vals.len());
+            for val in vals {
+                args.push(exec(val, executionContext)?);
+            }
+            let key = executionContext.struct_key(&name);
+            make_instance(&executionContext.structs, &key, &names, args)
+                .map_err(|kind| executionContext.error(kind, span))
+        }
+        ASTNode::Slice(list, from, to) => {
+            let list = exec(*list, executionContext)?;
+            let from = exec(*from, executionContext)?;
+            let to = exec(*to, executionContext)?;
+            slice_value(&list, &from, &to).map_err(|kind| executionContext.error(kind, span))
+        }
+        ASTNode::IfStatement(condition, ifbody, haselse, elsebody) => {
+            //= first, evaluate the conditino
+            let condition_tval = is_truthy(&exec(*condition, executionContext)?);
+            //= now, execute the if block (or dont)
+            if condition_tval {
+                executionContext.exec_block(ifbody)?;
+            } else if haselse {
+                //we're doing smth else
+                executionContext.exec_block(elsebody)?;
+            }
+            Ok(Value::None)
+        }
+        ASTNode::ConditionalLoop(condition, loopbody) => {
+            //check if we should be looping
+            while is_truthy(&exec(*condition.clone(), executionContext)?) {
+                //run a loop iteration (ergo, execute the trees!)
+                executionContext.exec_block(loopbody.clone())?;
+                if !executionContext.continue_loop() {
+                    break;
+                }
+            }
+            Ok(Value::None)
+        }
+        ASTNode::IncrementingLoop(a_itername, a_lowerbound, a_upperbound, loopbody) => {
+            //pull out our vars from all these boxes
+            //who says unboxing things in the holidays has to be limited to physical objects? (~ me, 12-27-21)
+            let itername = variable_name(*a_itername);
+            let (lowerbound, upperbound) =
+                exec_int_operands(*a_lowerbound, *a_upperbound, "in .. to", span, executionContext)?;
+            //now, iterate
+            for i in lowerbound..upperbound {
+                //the iterator lives in a scope of its own wrapped around the body, so it's gone once the loop is.
+                //it's a new one every time around, so functions made in different iterations don't share it.
+                let loop_scope = Scope::child_of(&executionContext.scope);
+                loop_scope.borrow_mut().vars.insert(itername.clone(), Value::Int(i));
+                executionContext.exec_in_scope(loopbody.clone(), loop_scope)?;
+                if !executionContext.continue_loop() {
+                    break;
+                }
+            }
+            Ok(Value::None)
+        }
+        ASTNode::EachLoop(a_itername, a_valname, list, loopbody) => {
+            let itername = variable_name(*a_itername);
+            let valname = a_valname.map(|valname| variable_name(*valname));
+            let list = exec(*list, executionContext)?;
+            let copy = loop_copy(&list).map_err(|kind| executionContext.error(kind, span))?;
+            let mut position = 0;
+            while let Some((first, second)) = loop_entry(&copy, position, valname.is_some()) {
+                //a fresh scope each time around, same as for `loop i in a to b`
+                let loop_scope = Scope::child_of(&executionContext.scope);
+                loop_scope.borrow_mut().vars.insert(itername.clone(), first);
+                if let Some(valname) = &valname {
+                    loop_scope.borrow_mut().vars.insert(valname.clone(), second);
+                }
+                executionContext.exec_in_scope(loopbody.clone(), loop_scope)?;
+                if !executionContext.continue_loop() {
+                    break;
+                }
+                position += 1;
+            }
+            Ok(Value::None)
+        }
+
+        ASTNode::LoopBreak => {
+            executionContext.flow = Flow::Break;
+            Ok(Value::None)
+        }
+        ASTNode::LoopContinue => {
+            executionContext.flow = Flow::Continue;
+            Ok(Value::None)
+        }
+        ASTNode::Return(valexp) => {
+            let val = exec(*valexp, executionContext)?;
+            executionContext.flow = Flow::Return(val);
+            Ok(Value::None)
+        }
+
+        //each piece turned into text and stuck together
+        ASTNode::Interpolated(parts) => {
+            let mut text = String::new();
+            for part in parts {
+                text += &exec(part, executionContext)?.to_string();
+            }
+            Ok(Value::Text(text))
+        }
+        ASTNode::Formatted(p1, spec) => {
+            let val = exec(*p1, executionContext)?;
+            format_value(&val, &spec)
+                .map(Value::Text)
+                .map_err(|kind| executionContext.error(kind, span))
+        }
+
+        //the atomic types just get mirrored through
+        ASTNode::Int(num) => Ok(Value::Int(num)),
+        ASTNode::Float(num) => Ok(Value::Float(num)),
+        ASTNode::Text(text) => Ok(Value::Text(text)),
+        ASTNode::Boolean(b) => Ok(Value::Boolean(b)),
+        _ => Ok(Value::None),
+    }
+}
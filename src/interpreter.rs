@@ -0,0 +1,549 @@
+use crate::ast::{declared_names, variable_name, ASTNode, Node, Span};
+use crate::builtins::{standard_natives, Native};
+use crate::compiler::{compile_program, disassemble, CompilerState, ModuleScope};
+use crate::error::{ParseError, RuntimeError, RuntimeErrorKind};
+use crate::exec::{exec, ExecutionContext, Scope};
+use crate::lexer::preprocess;
+use crate::parser::{parse_source, ParserState};
+use crate::source::SourceMap;
+use crate::value::{get_field, Callable, Function, Module, Value};
+use crate::vm::Vm;
+use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+//the two ways we have of running code, so main and the repl don't need to care which one they've got
+pub(crate) enum Engine {
+    Vm { cstate: Box<CompilerState>, vm: Vm },
+    TreeWalk(ExecutionContext),
+}
+
+impl Engine {
+    pub(crate) fn new(tree_walk: bool) -> Engine {
+        if tree_walk {
+            Engine::TreeWalk(ExecutionContext::new())
+        } else {
+            Engine::Vm {
+                cstate: Box::default(),
+                vm: Vm::default(),
+            }
+        }
+    }
+
+    //run some top-level trees, handing back the value of the last one
+    pub(crate) fn run(&mut self, trees: Vec<Node>, debug: bool) -> Result<Value, RuntimeError> {
+        //statements don't have a value, whichever engine ran them
+        let is_statement = trees.last().is_none_or(|tree| tree.ast.is_statement());
+        let val = match self {
+            Engine::Vm { cstate, vm } => {
+                let script = compile_program(&trees, cstate);
+                if debug {
+                    println!("\x1b[2;37m{}\x1b[0m", disassemble(&script));
+                }
+                vm.run(script, cstate)?
+            }
+            Engine::TreeWalk(execcontext) => {
+                execcontext.hoist_functions(&trees)?;
+                let mut ret_val = Value::None;
+                for tree in trees {
+                    if debug {
+                        println!("\x1b[32m Executing tree {:?}\x1b[0m", &tree);
+                    }
+                    ret_val = exec(tree, execcontext)?;
+                    if debug {
+                        println!("\x1b[2;37m Execution Context: {:?}\x1b[0m", &execcontext)
+                    }
+                }
+                ret_val
+            }
+        };
+        if is_statement {
+            Ok(Value::None)
+        } else {
+            Ok(val)
+        }
+    }
+
+    //every global that's been given a value so far, sorted by name. the natives everything starts out with
+    //aren't worth listing.
+    pub(crate) fn globals(&self) -> Vec<(String, Value)> {
+        let mut globals: Vec<(String, Value)> = match self {
+            Engine::Vm { cstate, vm } => cstate
+                .globalNames
+                .iter()
+                .zip(&vm.globals)
+                //modules' globals are theirs, not ours
+                .filter(|(id, _)| !id.contains("::"))
+                .filter_map(|(id, val)| val.clone().map(|val| (id.clone(), val)))
+                .collect(),
+            Engine::TreeWalk(execcontext) => execcontext
+                .globals
+                .borrow()
+                .vars
+                .iter()
+                .map(|(id, val)| (id.clone(), val.clone()))
+                .collect(),
+        };
+        globals.retain(|(_, val)| !matches!(val, Value::Function(function) if function.params().is_none()));
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+
+    //every global holding a script function, with its parameter names, sorted by name
+    pub(crate) fn functions(&self) -> Vec<(String, Vec<String>)> {
+        self.globals()
+            .into_iter()
+            .filter_map(|(id, val)| match val {
+                Value::Function(function) => function.params().map(|params| (id, params.to_vec())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    //call a global function from outside of any script. it can't have been called from anywhere, so not finding it
+    //(or handing it the wrong number of arguments) is an Error::Call rather than a RuntimeError.
+    pub(crate) fn call(&mut self, id: &str, args: Vec<Value>) -> Result<Value, Error> {
+        let function = match self.get_global(id) {
+            Some(Value::Function(function)) => function,
+            Some(val) => {
+                return Err(Error::Call(RuntimeErrorKind::TypeError(format!(
+                    "cannot call {}",
+                    val.type_name()
+                ))))
+            }
+            None => return Err(Error::Call(RuntimeErrorKind::UndefinedFunction(id.to_string()))),
+        };
+        check_arity(id, function.arity(), args.len())?;
+        match self {
+            Engine::Vm { cstate, vm } => vm.call_value(&function, args, cstate),
+            Engine::TreeWalk(execcontext) => execcontext.call(&function, args, None),
+        }
+        .map_err(Error::Runtime)
+    }
+
+    pub(crate) fn get_global(&self, id: &str) -> Option<Value> {
+        match self {
+            Engine::Vm { cstate, vm } => cstate
+                .globalNames
+                .iter()
+                .position(|name| name == id)
+                .and_then(|slot| vm.globals.get(slot).cloned().flatten()),
+            Engine::TreeWalk(execcontext) => execcontext.globals.borrow().vars.get(id).cloned(),
+        }
+    }
+
+    //defines the global if it isn't already, just like a top-level let
+    pub(crate) fn set_global(&mut self, id: &str, val: Value) {
+        match self {
+            Engine::Vm { cstate, vm } => {
+                let slot = cstate.global_slot(id) as usize;
+                vm.globals.resize(cstate.globalNames.len(), None);
+                vm.globals[slot] = Some(val);
+            }
+            Engine::TreeWalk(execcontext) => {
+                execcontext.globals.borrow_mut().vars.insert(id.to_string(), val);
+            }
+        }
+    }
+}
+
+//what enter_module put aside, for leave_module to go back to
+pub(crate) enum Namespace {
+    Vm(Option<ModuleScope>),
+    TreeWalk {
+        globals: Rc<RefCell<Scope>>,
+        scope: Rc<RefCell<Scope>>,
+        module: Option<String>,
+    },
+}
+
+impl Engine {
+    //from here on, run code as the module at `path`, whose top level defines `names`. all it starts out seeing is
+    //the natives - nothing the file importing it (or any other module) has defined.
+    pub(crate) fn enter_module(&mut self, path: &str, names: Vec<String>, natives: &[(String, Value)]) -> Namespace {
+        match self {
+            Engine::Vm { cstate, vm } => {
+                let outer = cstate.module.replace(ModuleScope {
+                    path: path.to_string(),
+                    names,
+                });
+                for (id, val) in natives {
+                    let slot = cstate.global_slot(id) as usize;
+                    vm.globals.resize(cstate.globalNames.len(), None);
+                    vm.globals[slot] = Some(val.clone());
+                }
+                Namespace::Vm(outer)
+            }
+            Engine::TreeWalk(execcontext) => {
+                //the natives sit one scope out, so they don't end up in what the module exports
+                let builtins = Rc::new(RefCell::new(Scope {
+                    vars: natives.iter().cloned().collect(),
+                    parent: None,
+                }));
+                let module = Scope::child_of(&builtins);
+                Namespace::TreeWalk {
+                    globals: std::mem::replace(&mut execcontext.globals, module.clone()),
+                    scope: std::mem::replace(&mut execcontext.scope, module),
+                    module: execcontext.module.replace(path.to_string()),
+                }
+            }
+        }
+    }
+
+    //go back to where we were before enter_module, handing back what the module defined, sorted by name
+    pub(crate) fn leave_module(&mut self, outer: Namespace) -> IndexMap<String, Value> {
+        let mut exports: IndexMap<String, Value> = match (self, outer) {
+            (Engine::Vm { cstate, vm }, Namespace::Vm(outer)) => {
+                let module = std::mem::replace(&mut cstate.module, outer).unwrap_or_default();
+                module
+                    .names
+                    .iter()
+                    .filter_map(|id| {
+                        let qualified = format!("{}::{}", module.path, id);
+                        let slot = cstate.globalNames.iter().position(|name| *name == qualified)?;
+                        let val = vm.globals.get(slot).cloned().flatten()?;
+                        Some((id.clone(), val))
+                    })
+                    .collect()
+            }
+            (Engine::TreeWalk(execcontext), Namespace::TreeWalk { globals, scope, module }) => {
+                execcontext.scope = scope;
+                execcontext.module = module;
+                let module = std::mem::replace(&mut execcontext.globals, globals);
+                let exports = module.borrow().vars.clone().into_iter().collect();
+                exports
+            }
+            _ => unreachable!("a namespace from the other engine"),
+        };
+        exports.sort_keys();
+        exports
+    }
+}
+
+fn check_arity(id: &str, expected: usize, found: usize) -> Result<(), Error> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(Error::Call(RuntimeErrorKind::ArityMismatch {
+            function: id.to_string(),
+            expected,
+            found,
+        }))
+    }
+}
+
+//= Embedding
+
+/// How an [`Interpreter`] should run things.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Run on the tree walker instead of the bytecode vm.
+    pub tree_walk: bool,
+    /// Print the parser's and the engine's inner workings as they go.
+    pub debug: bool,
+    /// What the script's `argc()` and `arg(n)` see.
+    pub scriptArgs: Vec<String>,
+}
+
+/// Anything that can go wrong handing code to an [`Interpreter`].
+#[derive(Debug)]
+pub enum Error {
+    /// The file couldn't be read.
+    Io { path: String, err: std::io::Error },
+    /// The code didn't parse, so none of it ran.
+    Syntax(Vec<ParseError>),
+    /// The code started running and then failed.
+    Runtime(RuntimeError),
+    /// `call_function` couldn't start the call at all.
+    Call(RuntimeErrorKind),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io { path, err } => write!(f, "couldn't read `{}`: {}", path, err),
+            Error::Syntax(errors) => {
+                let messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            Error::Runtime(err) => write!(f, "{}", err),
+            Error::Call(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// An interpreter that keeps its globals and functions between calls, so a host can feed it code bit by bit.
+///
+/// ```
+/// use scriptinglang::{Interpreter, Value};
+///
+/// let mut interpreter = Interpreter::new();
+/// interpreter.eval_str("fn double(x) { x * 2 }").unwrap();
+/// assert_eq!(interpreter.call_function("double", &[Value::Int(21)]).unwrap(), Value::Int(42));
+/// ```
+pub struct Interpreter {
+    sources: SourceMap,
+    pstate: ParserState,
+    engine: Engine,
+    debug: bool,
+    //every module imported so far, by where its file really is, so importing one again doesn't run it again
+    modules: HashMap<PathBuf, Value>,
+    //the files whose imports are being brought in right now, outermost first, for spotting cycles
+    importing: Vec<PathBuf>,
+    //every native registered so far, by name, for handing to each module as it's imported
+    natives: Vec<(String, Value)>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Interpreter {
+        let mut interpreter = Interpreter {
+            sources: SourceMap::default(),
+            pstate: ParserState::new(config.debug),
+            engine: Engine::new(config.tree_walk),
+            debug: config.debug,
+            modules: HashMap::new(),
+            importing: vec![],
+            natives: vec![],
+        };
+        for native in standard_natives(config.scriptArgs) {
+            interpreter.register(native);
+        }
+        interpreter
+    }
+
+    /// Makes a rust function callable from scripts as `name`, taking exactly `arity` arguments.
+    /// Registering a name that's already taken (`print` included) replaces it.
+    ///
+    /// ```
+    /// use scriptinglang::{Interpreter, RuntimeError, RuntimeErrorKind, Value};
+    ///
+    /// let mut interpreter = Interpreter::new();
+    /// interpreter.register_fn("half", 1, |args| match &args[0] {
+    ///     Value::Int(n) => Ok(Value::Float(*n as f64 / 2.0)),
+    ///     _ => Err(RuntimeError::new(RuntimeErrorKind::Native("can only halve ints".to_string()))),
+    /// });
+    /// assert_eq!(interpreter.eval_str("half(9)").unwrap(), Value::Float(4.5));
+    /// ```
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        arity: usize,
+        func: impl Fn(&[Value]) -> Result<Value, RuntimeError> + 'static,
+    ) {
+        self.register(Native {
+            name: name.to_string(),
+            arity,
+            func: Rc::new(func),
+        });
+    }
+
+    //natives are just globals holding a function, so registering a name that's already taken replaces what was there
+    fn register(&mut self, native: Native) {
+        let name = native.name.clone();
+        let val = Value::Function(Function::new(Callable::Native(native)));
+        self.natives.retain(|(id, _)| *id != name);
+        self.natives.push((name.clone(), val.clone()));
+        self.engine.set_global(&name, val);
+    }
+
+    /// Runs some code, handing back the value of its last expression (or none if it ended on a statement).
+    pub fn eval_str(&mut self, code: &str) -> Result<Value, Error> {
+        self.eval_source("<eval>", code)
+    }
+
+    /// Reads a file and runs it.
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Value, Error> {
+        let path = path.as_ref();
+        let code = fs::read_to_string(path).map_err(|err| Error::Io {
+            path: path.display().to_string(),
+            err,
+        })?;
+        self.eval_source(&path.display().to_string(), &code)
+    }
+
+    /// Like `eval_str`, with `name` being what errors call the code.
+    pub fn eval_source(&mut self, name: &str, code: &str) -> Result<Value, Error> {
+        let trees = self.load(name, code)?;
+        self.run(trees)
+    }
+
+    /// Parses some code for a later `run`. Nothing it declares exists until then - what later code gets to see is
+    /// the globals running it leaves behind, not anything from parsing it.
+    /// On a syntax error, it's as if the code was never seen.
+    pub fn load(&mut self, name: &str, code: &str) -> Result<Vec<Node>, Error> {
+        let offset = self.sources.add(name.to_string(), preprocess(code.to_string()));
+        let text = &self.sources.files[self.sources.files.len() - 1].text;
+        let saved = self.pstate.clone();
+        parse_source(text, offset, &mut self.pstate).map_err(|errors| {
+            self.pstate = saved;
+            Error::Syntax(errors)
+        })
+    }
+
+    /// Parses some code without it having any effect on what comes after.
+    pub fn parse(&mut self, name: &str, code: &str) -> Result<Vec<Node>, Error> {
+        let offset = self.sources.add(name.to_string(), preprocess(code.to_string()));
+        let text = &self.sources.files[self.sources.files.len() - 1].text;
+        parse_source(text, offset, &mut self.pstate.clone()).map_err(Error::Syntax)
+    }
+
+    /// Runs trees from `load`. Whatever they import gets brought in first.
+    pub fn run(&mut self, trees: Vec<Node>) -> Result<Value, Error> {
+        self.import_modules(&trees)?;
+        self.engine.run(trees, self.debug).map_err(Error::Runtime)
+    }
+
+    /// Calls a function the script declared.
+    pub fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        self.engine.call(name, args.to_vec())
+    }
+
+    /// The value of a global, if it's been given one.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.engine.get_global(name)
+    }
+
+    /// Gives a global a value, defining it if the script hasn't.
+    pub fn set_global(&mut self, name: &str, val: Value) {
+        self.engine.set_global(name, val)
+    }
+
+    /// Every global that's been given a value, sorted by name.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.engine.globals()
+    }
+
+    /// Every function that's been declared with its parameter names, sorted by name.
+    pub fn functions(&self) -> Vec<(String, Vec<String>)> {
+        self.engine.functions()
+    }
+
+    /// Everything this interpreter has been handed, for turning spans into file:line:col.
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    /// An error the way the command line shows it: pointing at the offending code, with a stack trace if it has one.
+    pub fn render_error(&self, err: &Error) -> String {
+        match err {
+            Error::Syntax(errors) => self.sources.render_parse_errors(errors.clone()),
+            Error::Runtime(err) => self.sources.render_runtime_error(err),
+            _ => format!("\x1b[31merror\x1b[0m: {}", err),
+        }
+    }
+}
+
+//= Modules
+
+impl Interpreter {
+    //bring in everything a file imports before any of it runs, the same way its functions are there from the start
+    fn import_modules(&mut self, trees: &[Node]) -> Result<(), Error> {
+        for tree in trees {
+            match &tree.ast {
+                ASTNode::Import(path, id) => {
+                    let module = self.import(path, tree.span)?;
+                    self.engine.set_global(&variable_name(*id.clone()), module);
+                }
+                ASTNode::ImportFrom(path, ids) => {
+                    let module = self.import(path, tree.span)?;
+                    for id in ids {
+                        let name = variable_name(id.clone());
+                        let val = get_field(&module, &name).map_err(|kind| import_error(kind, id.span))?;
+                        self.engine.set_global(&name, val);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    //the module an import names, running it first if nothing's imported it yet
+    fn import(&mut self, path: &str, span: Span) -> Result<Value, Error> {
+        let importer = self
+            .sources
+            .file(span.start)
+            .map(|file| file.name.clone())
+            .unwrap_or_default();
+        let file = find_module(path, &importer)
+            .ok_or_else(|| import_error(RuntimeErrorKind::ModuleNotFound(path.to_string()), span))?;
+        let key = fs::canonicalize(&file).unwrap_or_else(|_| file.clone());
+        if let Some(module) = self.modules.get(&key) {
+            return Ok(module.clone());
+        }
+        //the file at the very top counts as being imported too, so a module can't import it back
+        let mut chain = self.importing.clone();
+        if chain.is_empty() {
+            chain.extend(fs::canonicalize(&importer).ok());
+        }
+        if let Some(start) = chain.iter().position(|importing| *importing == key) {
+            let mut files: Vec<String> = chain[start..].iter().map(|file| file.display().to_string()).collect();
+            files.push(key.display().to_string());
+            return Err(import_error(RuntimeErrorKind::ImportCycle(files), span));
+        }
+        let code = fs::read_to_string(&file).map_err(|err| {
+            let kind = RuntimeErrorKind::ModuleUnreadable {
+                path: path.to_string(),
+                err: err.to_string(),
+            };
+            import_error(kind, span)
+        })?;
+        let offset = self.sources.add(file.display().to_string(), preprocess(code));
+        let text = &self.sources.files[self.sources.files.len() - 1].text;
+        //a module gets parsed on its own - none of the importer's names mean anything to it
+        let trees = parse_source(text, offset, &mut ParserState::new(self.debug)).map_err(Error::Syntax)?;
+
+        chain.push(key.clone());
+        let importing = std::mem::replace(&mut self.importing, chain);
+        let outer = self
+            .engine
+            .enter_module(&key.display().to_string(), declared_names(&trees), &self.natives);
+        let result = self
+            .import_modules(&trees)
+            .and_then(|_| self.engine.run(trees, self.debug).map_err(Error::Runtime));
+        let exports = self.engine.leave_module(outer);
+        self.importing = importing;
+        result?;
+
+        let module = Value::Module(Rc::new(Module {
+            name: path.to_string(),
+            exports,
+        }));
+        self.modules.insert(key, module.clone());
+        Ok(module)
+    }
+}
+
+//where the file an import names is: next to the file doing the importing, or failing that in one of the
+//directories on SK_PATH
+fn find_module(path: &str, importer: &str) -> Option<PathBuf> {
+    let here = Path::new(importer).parent().unwrap_or(Path::new(""));
+    let search_path = std::env::var_os("SK_PATH");
+    std::iter::once(here.to_path_buf())
+        .chain(search_path.iter().flat_map(std::env::split_paths))
+        .map(|dir| dir.join(path))
+        .find(|candidate| candidate.is_file())
+}
+
+//an import that couldn't happen, pointing at the import
+fn import_error(kind: RuntimeErrorKind, span: Span) -> Error {
+    Error::Runtime(RuntimeError {
+        span,
+        ..RuntimeError::new(kind)
+    })
+}
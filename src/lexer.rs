@@ -0,0 +1,460 @@
+use crate::ast::Span;
+use crate::error::{ParseError, ParseErrorKind};
+use logos::Logos;
+
+#[derive(Logos, Debug, PartialEq, Clone)]
+pub enum Token {
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)] //whitespace and other undesirables
+    #[regex(r"#([^#\n][^\n]*)?", logos::skip)] //# runs to the end of the line
+    Error,
+    //## is a doc comment. lex_source hands these to the `fn` after them rather than to the parser
+    #[regex(r"##[^\n]*")]
+    DocComment,
+    //block comments nest, so you can comment out code that already has one in it. lex_source drops them.
+    #[token("/*", lex_block_comment)]
+    BlockComment,
+    //declaration keywords / other important thinggs
+    #[token("let")]
+    KwLet,
+    #[token("fn")]
+    KwFn,
+    #[token("if")]
+    KwIf,
+    #[token("else")]
+    KwElse,
+    #[token("loop")]
+    KwLoop,
+    #[token("break")]
+    KwBreak,
+    #[token("continue")]
+    KwContinue,
+    #[token("return")]
+    KwReturn,
+    #[token("while")]
+    KwWhile,
+    #[token("struct")]
+    KwStruct,
+    #[token("impl")]
+    KwImpl,
+    //import "util.sk" as util; / from "util.sk" import checker;
+    #[token("import")]
+    KwImport,
+    #[token("from")]
+    KwFrom,
+    #[token("as")]
+    KwAs,
+    #[token("true")]
+    KwTrue,
+    #[token("false")]
+    KwFalse,
+    #[token("none")]
+    KwNone,
+    #[token("in")]
+    KwIn,
+    #[token("to")]
+    KwTo,
+    #[token(";")]
+    KwTerminator,
+    #[token(",")]
+    KwComma,
+    #[token("(")]
+    KwLParen,
+    #[token(")")]
+    KwRParen,
+    #[token("{")]
+    KwLBrace,
+    #[token("}")]
+    KwRBrace,
+    #[token("[")]
+    KwLBracket,
+    #[token("]")]
+    KwRBracket,
+    //the middle of a slice, xs[1..3]
+    #[token("..")]
+    KwRange,
+    //m.key
+    #[token(".")]
+    KwDot,
+    //{"key": value}
+    #[token(":")]
+    KwColon,
+    //operations
+    #[token("+=")]
+    OpAddEq,
+    #[token("-=")]
+    OpSubEq,
+    #[token("*=")]
+    OpMulEq,
+    #[token("/=")]
+    OpDivEq,
+    #[token("%=")]
+    OpModuloEq,
+    #[token("**=")]
+    OpPowEq,
+    #[token("+")]
+    OpAdd,
+    #[token("-")]
+    OpSub,
+    #[token("*")]
+    OpMul,
+    #[token("/")]
+    OpDiv,
+    #[token("==")]
+    OpEqCheck,
+    #[token("=")]
+    OpAssign,
+    #[token("!=")]
+    OpNeqCheck,
+    #[token("!")]
+    OpNot,
+    #[token(">")]
+    OpGtCheck,
+    #[token("<")]
+    OpLtCheck,
+    #[token(">=")]
+    OpGteCheck,
+    #[token("<=")]
+    OpLteCheck,
+    #[token("%")]
+    OpModulo,
+    #[token("**")]
+    OpPow,
+    //floor division, rounding down rather than towards zero
+    #[token("//")]
+    OpFloorDiv,
+    //bitwise operators, for ints only
+    #[token("&")]
+    OpBitAnd,
+    #[token("|")]
+    OpBitOr,
+    #[token("^")]
+    OpBitXor,
+    #[token("~")]
+    OpBitNot,
+    #[token("<<")]
+    OpShiftLeft,
+    #[token(">>")]
+    OpShiftRight,
+    #[token("&&")]
+    #[token("and")]
+    OpAnd,
+    #[token("||")]
+    #[token("or")]
+    OpOr,
+    //the keyword version of `!`, which binds looser - `not a == b` is `not (a == b)`
+    #[token("not")]
+    KwNot,
+    //"text", which can run over several lines, has escapes (\n \t \\ \" \u{1F600}) and {interpolations}
+    //that each have to close on the line they open on
+    #[token("\"", lex_text)]
+    Text,
+    //r"text", where a backslash is just a backslash and a brace is just a brace
+    #[token("r\"", lex_raw_text)]
+    RawText,
+    //good ol text & stuffs
+    #[regex("[a-zA-Z]+")]
+    Identifier,
+    #[regex("[0-9]+")]
+    Number,
+    //le big regexp. the point needs digits on both sides, so 1..3 stays a range.
+    #[regex(r"[0-9]+\.[0-9]+")]
+    DecimalNumber,
+}
+
+impl Token {
+    //how we refer to a token when telling someone we expected it
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Token::KwTerminator => "`;`",
+            Token::KwComma => "`,`",
+            Token::KwLParen => "`(`",
+            Token::KwRParen => "`)`",
+            Token::KwLBrace => "`{`",
+            Token::KwRBrace => "`}`",
+            Token::KwLBracket => "`[`",
+            Token::KwRBracket => "`]`",
+            Token::KwRange => "`..`",
+            Token::KwDot => "`.`",
+            Token::KwColon => "`:`",
+            Token::KwIn => "`in`",
+            Token::KwTo => "`to`",
+            Token::KwImport => "`import`",
+            Token::KwAs => "`as`",
+            Token::OpAssign => "`=`",
+            Token::Identifier => "an identifier",
+            _ => "something else",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct ParsableToken {
+    pub token: Token,
+    pub text: String,
+    pub span: Span,
+    //the ## lines right before a `fn`
+    pub doc: Option<String>,
+}
+
+//turn text that starts at `offset` in the SourceMap into tokens. anything the lexer can't make sense of goes in `errors`.
+pub fn lex_source(text: &str, offset: usize, errors: &mut Vec<ParseError>) -> Vec<ParsableToken> {
+    let mut lex = Token::lexer(text);
+    let mut tokens: Vec<ParsableToken> = vec![];
+    let mut doc: Vec<&str> = vec![];
+    while let Some(tok) = lex.next() {
+        let span = Span::new(offset + lex.span().start, offset + lex.span().end);
+        //an interpolation that never closes only spoils the string it's in, which still has its closing quote
+        if tok == Token::Error && lex.slice().starts_with('"') && lex.slice().ends_with('"') {
+            if let Err(Unterminated::Interpolation(open)) = text_end(&lex.slice()[1..]) {
+                let open = span.start + 1 + open;
+                errors.push(ParseError::new(
+                    ParseErrorKind::UnterminatedInterpolation,
+                    Span::new(open, open + 1),
+                ));
+                continue;
+            }
+        }
+        //a string that never ends swallows the rest of the file, so there's nothing left to lex anyway
+        if tok == Token::Error && (lex.slice().starts_with('"') || lex.slice().starts_with("r\"")) {
+            let quote = span.start + lex.slice().find('"').unwrap();
+            errors.push(ParseError::new(
+                ParseErrorKind::UnterminatedString,
+                Span::new(quote, quote + 1),
+            ));
+            break;
+        }
+        if tok == Token::Error && lex.slice().starts_with("/*") {
+            errors.push(ParseError::new(
+                ParseErrorKind::UnterminatedComment,
+                Span::new(span.start, span.start + 2),
+            ));
+            break;
+        }
+        if tok == Token::Error {
+            errors.push(ParseError::new(
+                ParseErrorKind::UnexpectedCharacter(lex.slice().to_string()),
+                span,
+            ));
+            continue;
+        }
+        match tok {
+            Token::BlockComment => continue,
+            Token::DocComment => {
+                let line = &lex.slice()[2..];
+                doc.push(line.strip_prefix(' ').unwrap_or(line));
+                continue;
+            }
+            _ => {}
+        }
+        //a doc comment that isn't followed by a fn doesn't document anything, so it just goes
+        let doc = match (tok == Token::KwFn, doc.is_empty()) {
+            (true, false) => Some(std::mem::take(&mut doc).join("\n")),
+            _ => {
+                doc.clear();
+                None
+            }
+        };
+        tokens.push(ParsableToken {
+            token: tok,
+            text: lex.slice().to_string(),
+            span,
+            doc,
+        });
+    }
+    tokens
+}
+
+//find the end of a "text" token. it isn't always the next quote along - an interpolation can have strings of its
+//own in it, like "{join(xs, ", ")}". a string that doesn't end takes the rest of the file with it, as an error.
+//one that only has an interpolation that doesn't end stops at its own closing quote, so the rest still gets lexed.
+fn lex_text(lex: &mut logos::Lexer<Token>) -> bool {
+    let text = lex.remainder();
+    match text_end(text) {
+        Ok(end) => {
+            lex.bump(end);
+            true
+        }
+        Err(Unterminated::Interpolation(open)) => {
+            lex.bump(quote_after(text, open).unwrap_or(text.len()));
+            false
+        }
+        Err(Unterminated::String) => {
+            lex.bump(text.len());
+            false
+        }
+    }
+}
+
+//skip to the */ that closes the /* we just saw, counting any /* ... */ pairs inside it on the way
+fn lex_block_comment(lex: &mut logos::Lexer<Token>) -> bool {
+    let bytes = lex.remainder().as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        match &bytes[i..i + 2] {
+            b"/*" => {
+                depth += 1;
+                i += 1;
+            }
+            b"*/" if depth == 0 => {
+                lex.bump(i + 2);
+                return true;
+            }
+            b"*/" => {
+                depth -= 1;
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    lex.bump(bytes.len());
+    false
+}
+
+fn lex_raw_text(lex: &mut logos::Lexer<Token>) -> bool {
+    match lex.remainder().find('"') {
+        Some(end) => {
+            lex.bump(end + 1);
+            true
+        }
+        None => {
+            lex.bump(lex.remainder().len());
+            false
+        }
+    }
+}
+
+//what kept a "text" token from ending
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Unterminated {
+    String,
+    //holds where the `{` is
+    Interpolation(usize),
+}
+
+//how far into `text` (which starts just after an opening quote) the closing quote is, counting the quote itself.
+//everything we care about is ascii, so going byte by byte can't land us in the middle of anything that matters.
+pub(crate) fn text_end(text: &str) -> Result<usize, Unterminated> {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i = escape_end(text, i),
+            b'"' => return Ok(i + 1),
+            //{{ and }} are just a brace
+            b'{' | b'}' if bytes.get(i + 1) == Some(&bytes[i]) => i += 1,
+            b'{' => i = interpolation_end(text, i).ok_or(Unterminated::Interpolation(i))?,
+            _ => {}
+        }
+        i += 1;
+    }
+    Err(Unterminated::String)
+}
+
+//how far into `text` the first quote after text[from] is, counting the quote itself. escaped quotes don't count.
+fn quote_after(text: &str, from: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut i = from + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i = escape_end(text, i),
+            b'"' => return Some(i + 1),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+//the last byte of the escape starting with the backslash at text[backslash]. mostly that's the byte after it,
+//but the braces in \u{...} aren't an interpolation. an escape never goes past the end of the text, or past the
+//quote ending the string when the braces don't get closed first - unescape can say what's wrong with it from there.
+pub(crate) fn escape_end(text: &str, backslash: usize) -> usize {
+    let rest = &text[backslash + 1..];
+    if rest.starts_with("u{") {
+        return match rest.find(['}', '"']) {
+            Some(close) if rest.as_bytes()[close] == b'}' => backslash + 1 + close,
+            Some(quote) => backslash + quote,
+            None => text.len() - 1,
+        };
+    }
+    (backslash + 1).min(text.len() - 1)
+}
+
+//where the `}` closing the interpolation that opens at text[open] is. strings inside it get skipped over whole.
+//it has to be closed on the line it's opened on, so a stray `{` can't take the string's closing quote (and
+//whatever comes after it) for the start of a string inside the interpolation.
+pub(crate) fn interpolation_end(text: &str, open: usize) -> Option<usize> {
+    let line_end = text[open..].find('\n').map_or(text.len(), |end| open + end);
+    let text = &text[..line_end];
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut i = open + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => i += text_end(&text[i + 1..]).ok()?,
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return Some(i),
+            b'}' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+//turn the inside of a "text" token into the text it stands for. `offset` is where that inside starts, for errors.
+pub fn unescape(text: &str, offset: usize) -> Result<String, ParseError> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some((_, 'n')) => Some('\n'),
+            Some((_, 't')) => Some('\t'),
+            Some((_, 'r')) => Some('\r'),
+            Some((_, '0')) => Some('\0'),
+            Some((_, '\\')) => Some('\\'),
+            Some((_, '"')) => Some('"'),
+            //\u{...} is between one and six hex digits naming a unicode scalar value
+            Some((u, 'u')) if text[u + 1..].starts_with('{') => {
+                let close = text[u..].find('}').map(|i| u + i);
+                let value = close.and_then(|close| {
+                    let digits = &text[u + 2..close];
+                    if (1..=6).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                        u32::from_str_radix(digits, 16).ok().and_then(char::from_u32)
+                    } else {
+                        None
+                    }
+                });
+                //move past the braces either way (or to the end if they never close), so an error covers all of it
+                let end = close.unwrap_or(text.len());
+                while chars.next_if(|(i, _)| *i <= end).is_some() {}
+                value
+            }
+            _ => None,
+        };
+        match escaped {
+            Some(c) => out.push(c),
+            None => {
+                let end = chars.peek().map(|(i, _)| *i).unwrap_or(text.len());
+                return Err(ParseError::new(
+                    ParseErrorKind::InvalidEscape(text[start..end].to_string()),
+                    Span::new(offset + start, offset + end),
+                ));
+            }
+        }
+    }
+    Ok(out)
+}
+
+//comments are handled by the lexer now, so all that's left to do here is deal with windows line endings
+pub fn preprocess(file: String) -> String {
+    //ew windows
+    file.replace('\r', "")
+}
@@ -0,0 +1,23 @@
+#![allow(non_snake_case)]
+/*
+A Tiny Scripting Language
+---------
+(C) Luminoso 2021 / All Rights Reserved
+*/
+//! The sack interpreter, for embedding. [`Interpreter`] is the place to start.
+
+pub mod ast;
+mod builtins;
+mod compiler;
+pub mod error;
+mod exec;
+mod interpreter;
+pub mod lexer;
+mod parser;
+pub mod source;
+mod value;
+mod vm;
+
+pub use error::{ParseError, ParseErrorKind, RuntimeError, RuntimeErrorKind, StackFrame};
+pub use interpreter::{Config, Error, Interpreter};
+pub use value::{Function, Instance, Module, Value};
@@ -0,0 +1,1202 @@
+use crate::ast::{ASTNode, FormatSpec, Node, Span};
+use crate::error::{ParseError, ParseErrorKind};
+use crate::lexer::{escape_end, interpolation_end, lex_source, text_end, unescape, ParsableToken, Token};
+use std::iter::Peekable;
+
+//a bit of fancyness to make a bit below look neat ig
+macro_rules! either {
+    ($test:expr => $true_expr:expr; $false_expr:expr) => {
+        if $test {
+            $true_expr
+        } else {
+            $false_expr
+        }
+    };
+}
+
+//binding powers for the infix operators. Higher numbers bind tighter; a left power lower than the right one makes
+//the operator left-associative, and the other way around makes it right-associative (that's how `=` chains).
+pub(crate) fn infix_binding_power(tok: &Token) -> Option<(u8, u8)> {
+    match tok {
+        Token::OpAssign
+        | Token::OpAddEq
+        | Token::OpSubEq
+        | Token::OpMulEq
+        | Token::OpDivEq
+        | Token::OpModuloEq
+        | Token::OpPowEq => Some((2, 1)),
+        Token::OpOr => Some((3, 4)),
+        Token::OpAnd => Some((5, 6)),
+        Token::OpEqCheck
+        | Token::OpNeqCheck
+        | Token::OpGtCheck
+        | Token::OpLtCheck
+        | Token::OpGteCheck
+        | Token::OpLteCheck => Some((COMPARISON_BINDING_POWER, 8)),
+        //the bitwise ones go between the comparisons and the arithmetic, so `x & 1 == 0` is `(x & 1) == 0`
+        Token::OpBitOr => Some((9, 10)),
+        Token::OpBitXor => Some((11, 12)),
+        Token::OpBitAnd => Some((13, 14)),
+        Token::OpShiftLeft | Token::OpShiftRight => Some((15, 16)),
+        Token::OpAdd | Token::OpSub => Some((17, 18)),
+        Token::OpMul | Token::OpDiv | Token::OpFloorDiv | Token::OpModulo => Some((19, 20)),
+        //** goes right to left, and binds tighter than a - in front of it: -2 ** 2 is -(2 ** 2), 2 ** 3 ** 2 is 2 ** 9
+        Token::OpPow => Some((23, 22)),
+        _ => None,
+    }
+}
+
+//unary -, ! and ~ bind tighter than every infix operator but **, so -a*b is (-a)*b
+pub(crate) const PREFIX_BINDING_POWER: u8 = 21;
+//`not` takes in the comparisons, but stops at and/or - `not a == b or c` is `(not (a == b)) or c`
+pub(crate) const COMPARISON_BINDING_POWER: u8 = 7;
+
+//consume the next token if it's the one we expect. returns whether it was there.
+pub(crate) fn skip_token(lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>, tok: Token) -> bool {
+    if lex.peek().map(|t| t.token == tok).unwrap_or(false) {
+        lex.next();
+        true
+    } else {
+        false
+    }
+}
+
+pub(crate) fn peek_token(lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>) -> Option<Token> {
+    lex.peek().map(|t| t.token.clone())
+}
+
+//where the next token starts - or the end of the file if we've run out
+pub(crate) fn peek_span(lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>, pstate: &ParserState) -> Span {
+    lex.peek().map(|t| t.span).unwrap_or(pstate.eof)
+}
+
+//the error for finding something other than what we wanted (or nothing at all) at the front of the stream
+pub(crate) fn unexpected(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    expected: &str,
+    pstate: &ParserState,
+) -> ParseError {
+    match lex.peek() {
+        Some(tokp) => ParseError::new(
+            ParseErrorKind::UnexpectedToken {
+                expected: expected.to_string(),
+                found: tokp.text.clone(),
+            },
+            tokp.span,
+        ),
+        None => ParseError::new(
+            ParseErrorKind::UnexpectedEof {
+                expected: expected.to_string(),
+            },
+            pstate.eof,
+        ),
+    }
+}
+
+//consume the next token, which has to be `tok`
+pub(crate) fn expect_token<'a>(
+    lex: &mut Peekable<std::slice::Iter<'a, ParsableToken>>,
+    tok: Token,
+    pstate: &ParserState,
+) -> Result<&'a ParsableToken, ParseError> {
+    if peek_token(lex) == Some(tok.clone()) {
+        return Ok(lex.next().unwrap());
+    }
+    Err(unexpected(lex, tok.describe(), pstate))
+}
+
+//statements end in a `;`, except for the last one in a block (or file) where it's optional
+pub(crate) fn expect_terminator(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &ParserState,
+) -> Result<(), ParseError> {
+    match peek_token(lex) {
+        Some(Token::KwTerminator) => {
+            lex.next();
+            Ok(())
+        }
+        Some(Token::KwRBrace) | None => Ok(()),
+        Some(_) => Err(unexpected(lex, Token::KwTerminator.describe(), pstate)),
+    }
+}
+
+//after an error, throw tokens away until we're somewhere sensible to start again:
+//just past a `;`, right before the `}` that closes the block we're in, or right before something that starts a statement.
+//`statement` is where the broken statement started - any `{` it opened before going wrong (a map literal's, say)
+//gets its `}` skipped along with everything else.
+pub(crate) fn synchronize(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    statement: Peekable<std::slice::Iter<'_, ParsableToken>>,
+) {
+    let consumed = statement.len() - lex.len();
+    let mut depth: usize = 0;
+    for tokp in statement.take(consumed) {
+        match tokp.token {
+            Token::KwLBrace => depth += 1,
+            Token::KwRBrace => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    while let Some(tok) = peek_token(lex) {
+        match tok {
+            Token::KwTerminator if depth == 0 => {
+                lex.next();
+                return;
+            }
+            Token::KwRBrace
+            | Token::KwLet
+            | Token::KwFn
+            | Token::KwStruct
+            | Token::KwImpl
+            | Token::KwImport
+            | Token::KwFrom
+            | Token::KwIf
+            | Token::KwLoop
+            | Token::KwReturn
+            | Token::KwBreak
+            | Token::KwContinue
+                if depth == 0 =>
+            {
+                return
+            }
+            Token::KwRBrace => depth -= 1,
+            Token::KwLBrace => depth += 1,
+            _ => {}
+        }
+        lex.next();
+    }
+}
+
+//parse a whole file, collecting every syntax error instead of giving up on the first one
+pub(crate) fn parse_program(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<Vec<Node>, Vec<ParseError>> {
+    let mut trees: Vec<Node> = vec![];
+    while lex.peek().is_some() {
+        let statement = lex.clone();
+        //imports only make sense up here, where there's a file for them to bring things into
+        let parsed = match peek_token(lex) {
+            Some(Token::KwImport) | Some(Token::KwFrom) => parse_import(lex, pstate),
+            _ => parse(lex, pstate),
+        };
+        match parsed {
+            Ok(tree) => {
+                if tree.ast != ASTNode::None {
+                    trees.push(tree);
+                }
+            }
+            Err(err) => {
+                pstate.errors.push(err);
+                synchronize(lex, statement);
+                //a stray `}` at the top level would stop synchronize dead, so get rid of it here
+                if peek_token(lex) == Some(Token::KwRBrace) {
+                    let span = peek_span(lex, pstate);
+                    pstate.errors.push(ParseError::new(
+                        ParseErrorKind::UnexpectedToken {
+                            expected: "a statement".to_string(),
+                            found: "}".to_string(),
+                        },
+                        span,
+                    ));
+                    lex.next();
+                }
+            }
+        }
+    }
+    if pstate.errors.is_empty() {
+        Ok(trees)
+    } else {
+        Err(std::mem::take(&mut pstate.errors))
+    }
+}
+
+//parse a single statement. expression statements eat their trailing `;`, the block statements (fn/if/loop) don't have one.
+pub(crate) fn parse(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    if pstate.debug {
+        println!(
+            "\x1b[33m [Parse] Parse called w/ next: {:?} pstate: {:?}\x1b[0m",
+            lex.peek(),
+            pstate
+        );
+    }
+    let start = peek_span(lex, pstate);
+    let stok = match peek_token(lex) {
+        Some(tok) => tok,
+        None => return Err(unexpected(lex, "a statement", pstate)),
+    };
+    match stok {
+        Token::KwLet => {
+            lex.next();
+            //next token should be the identifier
+            let v_id_tokp = expect_token(lex, Token::Identifier, pstate)?;
+            let v_id = Node::new(ASTNode::Variable(v_id_tokp.text.clone()), v_id_tokp.span);
+            if !skip_token(lex, Token::OpAssign) {
+                let span = peek_span(lex, pstate);
+                return Err(ParseError::new(ParseErrorKind::MissingAssign, span));
+            }
+            let assign = parse_expression(lex, 0, pstate)?;
+            expect_terminator(lex, pstate)?;
+            let span = start.to(assign.span);
+            Ok(Node::new(
+                ASTNode::Set(Box::new(v_id), Box::new(assign)),
+                span,
+            ))
+        }
+        //a `fn` with no name is a function expression, which gets handled like any other expression
+        Token::KwFn if lex.clone().nth(1).map(|t| &t.token) != Some(&Token::KwLParen) => {
+            let doc = lex.next().unwrap().doc.clone();
+            parse_function(lex, start, doc, pstate)
+        }
+        Token::KwStruct => {
+            lex.next();
+            parse_struct(lex, start, pstate)
+        }
+        Token::KwImpl => {
+            lex.next();
+            parse_impl(lex, start, pstate)
+        }
+        Token::KwIf => {
+            lex.next();
+            parse_if(lex, start, pstate)
+        }
+        Token::KwLoop => {
+            lex.next();
+            parse_loop(lex, start, pstate)
+        }
+        Token::KwImport | Token::KwFrom => Err(ParseError::new(
+            ParseErrorKind::Misplaced(lex.next().unwrap().text.clone()),
+            start,
+        )),
+        Token::KwBreak | Token::KwContinue => {
+            let tokp = lex.next().unwrap();
+            if pstate.loopDepth == 0 {
+                return Err(ParseError::new(
+                    ParseErrorKind::Misplaced(tokp.text.clone()),
+                    start,
+                ));
+            }
+            expect_terminator(lex, pstate)?;
+            let ast = either!(stok == Token::KwBreak => ASTNode::LoopBreak; ASTNode::LoopContinue);
+            Ok(Node::new(ast, start))
+        }
+        Token::KwReturn => {
+            lex.next();
+            if !pstate.inFunction {
+                return Err(ParseError::new(
+                    ParseErrorKind::Misplaced("return".to_string()),
+                    start,
+                ));
+            }
+            //a bare `return;` hands back none
+            let value = match peek_token(lex) {
+                Some(Token::KwTerminator) | Some(Token::KwRBrace) | None => {
+                    Node::new(ASTNode::None, start)
+                }
+                _ => parse_expression(lex, 0, pstate)?,
+            };
+            expect_terminator(lex, pstate)?;
+            let span = start.to(value.span);
+            Ok(Node::new(ASTNode::Return(Box::new(value)), span))
+        }
+        Token::KwTerminator => {
+            //stray semicolon, nothing to see here
+            lex.next();
+            Ok(Node::new(ASTNode::None, start))
+        }
+        _ => {
+            let tree = parse_expression(lex, 0, pstate)?;
+            expect_terminator(lex, pstate)?;
+            Ok(tree)
+        }
+    }
+}
+
+//parse statements between a pair of braces. also hands back the span of the closing brace so callers can cover the whole block.
+//a broken statement gets recorded in the parser state and skipped, so one typo doesn't take the rest of the block with it.
+pub(crate) fn parse_block(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<(Vec<Node>, Span), ParseError> {
+    let open = expect_token(lex, Token::KwLBrace, pstate)?.span;
+    let mut body: Vec<Node> = vec![];
+    loop {
+        let statement = lex.clone();
+        match peek_token(lex) {
+            Some(Token::KwRBrace) => {
+                let end = peek_span(lex, pstate);
+                lex.next();
+                return Ok((body, end));
+            }
+            None => return Err(ParseError::new(ParseErrorKind::UnclosedBrace, open)),
+            Some(tok) => match parse(lex, pstate) {
+                Ok(tree) => {
+                    if pstate.debug {
+                        println!("\x1b[36m[Block] Got Result: {:?}\x1b[0m", tree);
+                    }
+                    //a lone `none` is the only None we keep in a body, everything else that comes back as None is noise
+                    if tree.ast != ASTNode::None || tok == Token::KwNone {
+                        body.push(tree);
+                    }
+                }
+                Err(err) => {
+                    pstate.errors.push(err);
+                    synchronize(lex, statement);
+                }
+            },
+        }
+    }
+}
+
+//import "<path>" as <id>; or from "<path>" import <id>, ...;
+pub(crate) fn parse_import(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let stok = lex.next().unwrap();
+    let path = parse_import_path(lex, pstate)?;
+    let ast = if stok.token == Token::KwImport {
+        expect_token(lex, Token::KwAs, pstate)?;
+        let id_tokp = expect_token(lex, Token::Identifier, pstate)?;
+        let id = Node::new(ASTNode::Variable(id_tokp.text.clone()), id_tokp.span);
+        ASTNode::Import(path, Box::new(id))
+    } else {
+        expect_token(lex, Token::KwImport, pstate)?;
+        let mut names: Vec<Node> = vec![];
+        loop {
+            let id_tokp = expect_token(lex, Token::Identifier, pstate)?;
+            names.push(Node::new(ASTNode::Variable(id_tokp.text.clone()), id_tokp.span));
+            if !skip_token(lex, Token::KwComma) {
+                break;
+            }
+        }
+        ASTNode::ImportFrom(path, names)
+    };
+    let end = match &ast {
+        ASTNode::Import(_, id) => id.span,
+        ASTNode::ImportFrom(_, ids) => ids[ids.len() - 1].span,
+        _ => stok.span,
+    };
+    expect_terminator(lex, pstate)?;
+    Ok(Node::new(ast, stok.span.to(end)))
+}
+
+//the file an import names. it gets found before anything runs, so it has to be plain text with nothing to interpolate.
+fn parse_import_path(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<String, ParseError> {
+    let text = match lex.peek() {
+        Some(tokp) if matches!(tokp.token, Token::Text | Token::RawText) => tokp.text.clone(),
+        _ => return Err(unexpected(lex, "a file name", pstate)),
+    };
+    let path = parse_operand(lex, pstate)?;
+    match path.ast {
+        ASTNode::Text(path) => Ok(path),
+        _ => Err(ParseError::new(
+            ParseErrorKind::UnexpectedToken {
+                expected: "a file name".to_string(),
+                found: text,
+            },
+            path.span,
+        )),
+    }
+}
+
+//fn <id>(<params>) { <body> } - the `fn` has already been eaten, and `doc` is whatever ## lines it had
+pub(crate) fn parse_function(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    start: Span,
+    doc: Option<String>,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let f_id_tokp = expect_token(lex, Token::Identifier, pstate)?;
+    let f_id = Node::new(ASTNode::Text(f_id_tokp.text.clone()), f_id_tokp.span);
+    let (params, function_ast, end) = parse_function_rest(lex, pstate)?;
+    Ok(Node::new(
+        ASTNode::FunctionDecl(Box::new(f_id), params, function_ast, doc),
+        start.to(end),
+    ))
+}
+
+//(<params>) { <body> } - everything after the name, which named and anonymous functions have in common
+pub(crate) fn parse_function_rest(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<(Vec<Node>, Vec<Node>, Span), ParseError> {
+    let open = expect_token(lex, Token::KwLParen, pstate)?.span;
+    let mut params: Vec<Node> = vec![];
+    while !skip_token(lex, Token::KwRParen) {
+        if lex.peek().is_none() {
+            return Err(ParseError::new(ParseErrorKind::UnclosedParen, open));
+        }
+        let tokp = expect_token(lex, Token::Identifier, pstate)?;
+        params.push(Node::new(ASTNode::Variable(tokp.text.clone()), tokp.span));
+        if peek_token(lex) != Some(Token::KwRParen) {
+            expect_token(lex, Token::KwComma, pstate)?;
+        }
+    }
+    if pstate.debug {
+        println!("\x1b[35m[Fn Decl] Finished getting parameters: {:?}\x1b[0m", params);
+    }
+    let mut function_pstate = ParserState {
+        errors: vec![],
+        loopDepth: 0,
+        inFunction: true,
+        noStructLiterals: false,
+        eof: pstate.eof,
+        debug: pstate.debug,
+    };
+    let body = parse_block(lex, &mut function_pstate);
+    //the function body's mistakes are the file's mistakes
+    pstate.errors.append(&mut function_pstate.errors);
+    let (function_ast, end) = body?;
+    Ok((params, function_ast, end))
+}
+
+//struct <id> { <field>, ... } - the `struct` has already been eaten
+pub(crate) fn parse_struct(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    start: Span,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let name = expect_token(lex, Token::Identifier, pstate)?.text.clone();
+    let open = expect_token(lex, Token::KwLBrace, pstate)?.span;
+    let mut fields: Vec<String> = vec![];
+    while peek_token(lex) == Some(Token::Identifier) {
+        let field = lex.next().unwrap();
+        //nothing stops us carrying on after this one
+        if fields.contains(&field.text) {
+            let duplicate = ParseErrorKind::DuplicateField(field.text.clone());
+            pstate.errors.push(ParseError::new(duplicate, field.span));
+        } else {
+            fields.push(field.text.clone());
+        }
+        if !skip_token(lex, Token::KwComma) {
+            break;
+        }
+    }
+    let end = close_brace(lex, open, pstate)?;
+    Ok(Node::new(ASTNode::StructDecl(name, fields), start.to(end)))
+}
+
+//impl <id> { fn <method>(self, ...) { } ... } - the `impl` has already been eaten
+pub(crate) fn parse_impl(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    start: Span,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let name = expect_token(lex, Token::Identifier, pstate)?.text.clone();
+    let open = expect_token(lex, Token::KwLBrace, pstate)?.span;
+    let mut methods: Vec<Node> = vec![];
+    //like a block, a broken method (or something that isn't one) gets recorded and skipped
+    loop {
+        match peek_token(lex) {
+            Some(Token::KwRBrace) => break,
+            None => return Err(ParseError::new(ParseErrorKind::UnclosedBrace, open)),
+            Some(Token::KwFn) => {
+                let statement = lex.clone();
+                let fn_tokp = lex.next().unwrap();
+                match parse_function(lex, fn_tokp.span, fn_tokp.doc.clone(), pstate) {
+                    Ok(method) => methods.push(method),
+                    Err(err) => {
+                        pstate.errors.push(err);
+                        synchronize(lex, statement);
+                    }
+                }
+            }
+            Some(_) => {
+                let err = unexpected(lex, "a method", pstate);
+                pstate.errors.push(err);
+                let statement = lex.clone();
+                lex.next();
+                synchronize(lex, statement);
+            }
+        }
+    }
+    let end = close_brace(lex, open, pstate)?;
+    Ok(Node::new(ASTNode::ImplBlock(name, methods), start.to(end)))
+}
+
+//the `}` that goes with the `{` at `open`, for the braces that hold something other than statements
+pub(crate) fn close_brace(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    open: Span,
+    pstate: &ParserState,
+) -> Result<Span, ParseError> {
+    let end = peek_span(lex, pstate);
+    if skip_token(lex, Token::KwRBrace) {
+        return Ok(end);
+    }
+    if matches!(peek_token(lex), Some(Token::KwTerminator) | None) {
+        return Err(ParseError::new(ParseErrorKind::UnclosedBrace, open));
+    }
+    Err(unexpected(lex, Token::KwRBrace.describe(), pstate))
+}
+
+//the condition of an if or `loop while`, or a loop's bounds. a `{` straight after a name starts the body here.
+pub(crate) fn parse_condition(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let outer = std::mem::replace(&mut pstate.noStructLiterals, true);
+    let condition = parse_expression(lex, 0, pstate);
+    pstate.noStructLiterals = outer;
+    condition
+}
+
+//an expression inside some kind of brackets, where struct literals are back on the table
+pub(crate) fn parse_nested_expression(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let outer = std::mem::replace(&mut pstate.noStructLiterals, false);
+    let expression = parse_expression(lex, 0, pstate);
+    pstate.noStructLiterals = outer;
+    expression
+}
+
+//if <cond> { <body> } [else { <body> } | else if ...] - the `if` has already been eaten
+pub(crate) fn parse_if(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    start: Span,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let condition = parse_condition(lex, pstate)?;
+    if pstate.debug {
+        println!(
+            "\x1b[32m[IfStatement - Conditional] Got Conditional: {:?} \x1b[0m",
+            condition
+        );
+    }
+    let (if_ast, mut end) = parse_block(lex, pstate)?;
+    let has_else = skip_token(lex, Token::KwElse);
+    let mut else_ast: Vec<Node> = vec![];
+    if has_else {
+        //an else is either followed by a plain block, or by another if which we nest inside the else body
+        let else_start = peek_span(lex, pstate);
+        if skip_token(lex, Token::KwIf) {
+            let nested = parse_if(lex, else_start, pstate)?;
+            end = nested.span;
+            else_ast.push(nested);
+        } else {
+            (else_ast, end) = parse_block(lex, pstate)?;
+        }
+    }
+    Ok(Node::new(
+        ASTNode::IfStatement(Box::new(condition), if_ast, has_else, else_ast),
+        start.to(end),
+    ))
+}
+
+//loop while <cond> { } | loop <id> in <lower> to <upper> { } | loop <id>[, <id>] in <list or map> { }
+//- the `loop` has already been eaten
+pub(crate) fn parse_loop(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    start: Span,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    if skip_token(lex, Token::KwWhile) {
+        let condition = parse_condition(lex, pstate)?;
+        if pstate.debug {
+            println!(
+                "\x1b[32m[Loop (Conditional Variety)] Got Conditional: {:?} \x1b[0m",
+                condition
+            );
+        }
+        let (loop_ast, end) = parse_loop_body(lex, pstate)?;
+        return Ok(Node::new(
+            ASTNode::ConditionalLoop(Box::new(condition), loop_ast),
+            start.to(end),
+        ));
+    }
+    if peek_token(lex) == Some(Token::Identifier) {
+        let iter_tokp = lex.next().unwrap();
+        let iter_id = Node::new(ASTNode::Variable(iter_tokp.text.clone()), iter_tokp.span);
+        //loop k, v in m - only makes sense for going through a list or map
+        let val_id = if skip_token(lex, Token::KwComma) {
+            let val_tokp = expect_token(lex, Token::Identifier, pstate)?;
+            let val_id = Node::new(ASTNode::Variable(val_tokp.text.clone()), val_tokp.span);
+            Some(Box::new(val_id))
+        } else {
+            None
+        };
+        expect_token(lex, Token::KwIn, pstate)?;
+        let lower_bound = parse_condition(lex, pstate)?;
+        //no `to` means we're going through a list or map
+        if val_id.is_some() || peek_token(lex) == Some(Token::KwLBrace) {
+            let (loop_ast, end) = parse_loop_body(lex, pstate)?;
+            return Ok(Node::new(
+                ASTNode::EachLoop(Box::new(iter_id), val_id, Box::new(lower_bound), loop_ast),
+                start.to(end),
+            ));
+        }
+        expect_token(lex, Token::KwTo, pstate)?;
+        let upper_bound = parse_condition(lex, pstate)?;
+        let (loop_ast, end) = parse_loop_body(lex, pstate)?;
+        return Ok(Node::new(
+            ASTNode::IncrementingLoop(
+                Box::new(iter_id),
+                Box::new(lower_bound),
+                Box::new(upper_bound),
+                loop_ast,
+            ),
+            start.to(end),
+        ));
+    }
+    Err(unexpected(lex, "`while` or a loop variable", pstate))
+}
+
+//a loop body is just a block that break and continue are allowed in
+pub(crate) fn parse_loop_body(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<(Vec<Node>, Span), ParseError> {
+    pstate.loopDepth += 1;
+    let body = parse_block(lex, pstate);
+    pstate.loopDepth -= 1;
+    body
+}
+
+//precedence climbing: grab an operand, then keep folding in operators for as long as they bind at least as tightly as min_bp
+pub(crate) fn parse_expression(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    min_bp: u8,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let operand = parse_operand(lex, pstate)?;
+    let mut lhs = parse_postfix(lex, operand, pstate)?;
+    while let Some(op) = peek_token(lex) {
+        let (l_bp, r_bp) = match infix_binding_power(&op) {
+            Some(bp) => bp,
+            None => break,
+        };
+        if l_bp < min_bp {
+            break;
+        }
+        lex.next();
+        let rhs = parse_expression(lex, r_bp, pstate)?;
+        if pstate.debug {
+            println!(
+                "\x1b[36m[Expression] {:?} with lhs: {:?} rhs: {:?}\x1b[0m",
+                op, lhs, rhs
+            );
+        }
+        lhs = build_binary(op, lhs, rhs)?;
+    }
+    Ok(lhs)
+}
+
+//calls, indexing, fields and methods bind tighter than anything else, and work on anything - f(1)(2) calls whatever
+//f(1) hands back, xs[0][1] indexes into whatever xs[0] is
+pub(crate) fn parse_postfix(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    mut lhs: Node,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    loop {
+        match peek_token(lex) {
+            Some(Token::KwLParen) => {
+                let (params, end) = parse_arguments(lex, pstate)?;
+                if pstate.debug {
+                    println!("\x1b[35m[Fn Call] Parameter List: {:?}\x1b[0m", params);
+                }
+                let span = lhs.span.to(end);
+                lhs = Node::new(ASTNode::FunctionCall(Box::new(lhs), params), span);
+            }
+            Some(Token::KwLBracket) => lhs = parse_index(lex, lhs, pstate)?,
+            Some(Token::KwDot) => {
+                lex.next();
+                let name = expect_token(lex, Token::Identifier, pstate)?;
+                //thing.name(args) is a method call rather than a call of whatever's in the field
+                if peek_token(lex) == Some(Token::KwLParen) {
+                    let (params, end) = parse_arguments(lex, pstate)?;
+                    let span = lhs.span.to(end);
+                    lhs = Node::new(ASTNode::MethodCall(Box::new(lhs), name.text.clone(), params), span);
+                    continue;
+                }
+                let span = lhs.span.to(name.span);
+                lhs = Node::new(ASTNode::Field(Box::new(lhs), name.text.clone()), span);
+            }
+            _ => return Ok(lhs),
+        }
+    }
+}
+
+//[<index>] or [<from>..<to>], where either end of the slice can be left off
+pub(crate) fn parse_index(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    list: Node,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let open = expect_token(lex, Token::KwLBracket, pstate)?.span;
+    let open_bound = |lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>, pstate: &ParserState| {
+        Node::new(ASTNode::None, peek_span(lex, pstate))
+    };
+    let from = match peek_token(lex) {
+        Some(Token::KwRange) => open_bound(lex, pstate),
+        _ => parse_nested_expression(lex, pstate)?,
+    };
+    let to = if skip_token(lex, Token::KwRange) {
+        match peek_token(lex) {
+            Some(Token::KwRBracket) => Some(open_bound(lex, pstate)),
+            _ => Some(parse_nested_expression(lex, pstate)?),
+        }
+    } else {
+        None
+    };
+    let end = close_bracket(lex, open, pstate)?;
+    let span = list.span.to(end);
+    let ast = match to {
+        Some(to) => ASTNode::Slice(Box::new(list), Box::new(from), Box::new(to)),
+        None => ASTNode::Index(Box::new(list), Box::new(from)),
+    };
+    Ok(Node::new(ast, span))
+}
+
+//the `]` that goes with the `[` at `open`, handing back its span
+pub(crate) fn close_bracket(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    open: Span,
+    pstate: &ParserState,
+) -> Result<Span, ParseError> {
+    let end = peek_span(lex, pstate);
+    if skip_token(lex, Token::KwRBracket) {
+        return Ok(end);
+    }
+    //running into the end of the statement means the bracket never got closed
+    if matches!(
+        peek_token(lex),
+        Some(Token::KwTerminator) | Some(Token::KwRBrace) | None
+    ) {
+        return Err(ParseError::new(ParseErrorKind::UnclosedBracket, open));
+    }
+    Err(unexpected(lex, Token::KwRBracket.describe(), pstate))
+}
+
+pub(crate) fn build_binary(op: Token, lhs: Node, rhs: Node) -> Result<Node, ParseError> {
+    let span = lhs.span.to(rhs.span);
+    let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+    let ast = match op {
+        Token::OpAdd => ASTNode::Add(lhs, rhs),
+        Token::OpSub => ASTNode::Sub(lhs, rhs),
+        Token::OpMul => ASTNode::Mul(lhs, rhs),
+        Token::OpDiv => ASTNode::Div(lhs, rhs),
+        Token::OpModulo => ASTNode::Modulo(lhs, rhs),
+        Token::OpPow => ASTNode::Pow(lhs, rhs),
+        Token::OpFloorDiv => ASTNode::FloorDiv(lhs, rhs),
+        Token::OpBitAnd => ASTNode::BitAnd(lhs, rhs),
+        Token::OpBitOr => ASTNode::BitOr(lhs, rhs),
+        Token::OpBitXor => ASTNode::BitXor(lhs, rhs),
+        Token::OpShiftLeft => ASTNode::ShiftLeft(lhs, rhs),
+        Token::OpShiftRight => ASTNode::ShiftRight(lhs, rhs),
+        Token::OpEqCheck => ASTNode::EqCheck(lhs, rhs),
+        Token::OpNeqCheck => ASTNode::NeqCheck(lhs, rhs),
+        Token::OpGtCheck => ASTNode::GtCheck(lhs, rhs),
+        Token::OpLtCheck => ASTNode::LtCheck(lhs, rhs),
+        Token::OpGteCheck => ASTNode::GteCheck(lhs, rhs),
+        Token::OpLteCheck => ASTNode::LteCheck(lhs, rhs),
+        Token::OpAnd => ASTNode::And(lhs, rhs),
+        Token::OpOr => ASTNode::Or(lhs, rhs),
+        //the only valid things to assign to are vars, elements and fields
+        Token::OpAssign
+        | Token::OpAddEq
+        | Token::OpSubEq
+        | Token::OpMulEq
+        | Token::OpDivEq
+        | Token::OpModuloEq
+        | Token::OpPowEq => {
+            if !matches!(lhs.ast, ASTNode::Variable(_) | ASTNode::Index(..) | ASTNode::Field(..)) {
+                return Err(ParseError::new(
+                    ParseErrorKind::InvalidAssignTarget,
+                    lhs.span,
+                ));
+            }
+            match op {
+                Token::OpAssign => ASTNode::Change(lhs, rhs),
+                Token::OpAddEq => ASTNode::AddEq(lhs, rhs),
+                Token::OpSubEq => ASTNode::SubEq(lhs, rhs),
+                Token::OpMulEq => ASTNode::MulEq(lhs, rhs),
+                Token::OpDivEq => ASTNode::DivEq(lhs, rhs),
+                Token::OpModuloEq => ASTNode::ModuloEq(lhs, rhs),
+                _ => ASTNode::PowEq(lhs, rhs),
+            }
+        }
+        _ => unreachable!("{:?} has a binding power but isn't a binary operator", op),
+    };
+    Ok(Node::new(ast, span))
+}
+
+//the things that can start an expression: atoms, calls, parens and the prefix operators
+pub(crate) fn parse_operand(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<Node, ParseError> {
+    let stok = match peek_token(lex) {
+        Some(tok) => tok,
+        None => return Err(unexpected(lex, "an expression", pstate)),
+    };
+    //anything that can't start an expression stays put, so the error points at it
+    if !matches!(
+        stok,
+        Token::Number
+            | Token::DecimalNumber
+            | Token::Text
+            | Token::RawText
+            | Token::KwTrue
+            | Token::KwFalse
+            | Token::KwNone
+            | Token::Identifier
+            | Token::KwLParen
+            | Token::OpSub
+            | Token::OpNot
+            | Token::KwNot
+            | Token::OpBitNot
+            | Token::KwFn
+            | Token::KwLBracket
+            | Token::KwLBrace
+    ) {
+        return Err(unexpected(lex, "an expression", pstate));
+    }
+    let tokp = lex.next().unwrap();
+    let sstr = tokp.text.clone();
+    let start = tokp.span;
+    match stok {
+        //an int literal too big for an i64 is a mistake, not something to quietly round
+        Token::Number => match sstr.parse::<i64>() {
+            Ok(num) => Ok(Node::new(ASTNode::Int(num), start)),
+            Err(_) => Err(ParseError::new(ParseErrorKind::InvalidNumber(sstr), start)),
+        },
+        Token::DecimalNumber => match sstr.parse::<f64>() {
+            Ok(num) => Ok(Node::new(ASTNode::Float(num), start)),
+            Err(_) => Err(ParseError::new(ParseErrorKind::InvalidNumber(sstr), start)),
+        },
+        Token::Text => parse_text(&sstr, start, pstate),
+        Token::RawText => Ok(Node::new(ASTNode::Text(sstr[2..sstr.len() - 1].to_string()), start)),
+        Token::KwTrue | Token::KwFalse => Ok(Node::new(
+            either!(stok == Token::KwTrue => ASTNode::Boolean(true); ASTNode::Boolean(false)),
+            start,
+        )),
+        Token::KwNone => Ok(Node::new(ASTNode::None, start)),
+        //Point { x: 1, y: 2 } - unless we're in an if or loop's head, where the `{` would be the start of the body
+        Token::Identifier if peek_token(lex) == Some(Token::KwLBrace) && !pstate.noStructLiterals => {
+            let open = lex.next().unwrap().span;
+            let mut fields: Vec<(String, Node)> = vec![];
+            while peek_token(lex) == Some(Token::Identifier) {
+                let field = lex.next().unwrap();
+                expect_token(lex, Token::KwColon, pstate)?;
+                let val = parse_nested_expression(lex, pstate)?;
+                if fields.iter().any(|(name, _)| *name == field.text) {
+                    let duplicate = ParseErrorKind::DuplicateField(field.text.clone());
+                    pstate.errors.push(ParseError::new(duplicate, field.span));
+                } else {
+                    fields.push((field.text.clone(), val));
+                }
+                if !skip_token(lex, Token::KwComma) {
+                    break;
+                }
+            }
+            let end = close_brace(lex, open, pstate)?;
+            Ok(Node::new(ASTNode::StructLiteral(sstr, fields), start.to(end)))
+        }
+        //the dreaded one - Identifiers. if there's a `(` after, parse_postfix turns it into a call - whether or not
+        //there's any such function (yet) gets worked out when it runs.
+        Token::Identifier => Ok(Node::new(ASTNode::Variable(sstr), start)),
+        Token::KwFn => {
+            let (params, body, end) = parse_function_rest(lex, pstate)?;
+            Ok(Node::new(ASTNode::FunctionExpr(params, body), start.to(end)))
+        }
+        //[a, b, c] - a trailing comma is fine
+        Token::KwLBracket => {
+            let mut items: Vec<Node> = vec![];
+            while !matches!(
+                peek_token(lex),
+                Some(Token::KwRBracket) | Some(Token::KwTerminator) | Some(Token::KwRBrace) | None
+            ) {
+                items.push(parse_nested_expression(lex, pstate)?);
+                if !skip_token(lex, Token::KwComma) {
+                    break;
+                }
+            }
+            let end = close_bracket(lex, start, pstate)?;
+            Ok(Node::new(ASTNode::ListLiteral(items), start.to(end)))
+        }
+        //{key: value, ...} - a `{` can't start a block in the middle of an expression, so this is always a map
+        Token::KwLBrace => {
+            let mut entries: Vec<(Node, Node)> = vec![];
+            while !matches!(
+                peek_token(lex),
+                Some(Token::KwRBrace) | Some(Token::KwTerminator) | None
+            ) {
+                let key = parse_nested_expression(lex, pstate)?;
+                expect_token(lex, Token::KwColon, pstate)?;
+                let val = parse_nested_expression(lex, pstate)?;
+                entries.push((key, val));
+                if !skip_token(lex, Token::KwComma) {
+                    break;
+                }
+            }
+            let end = close_brace(lex, start, pstate)?;
+            Ok(Node::new(ASTNode::MapLiteral(entries), start.to(end)))
+        }
+        Token::KwLParen => {
+            let mut inner = parse_nested_expression(lex, pstate)?;
+            let end = peek_span(lex, pstate);
+            if !skip_token(lex, Token::KwRParen) {
+                if matches!(peek_token(lex), Some(Token::KwTerminator) | Some(Token::KwRBrace) | None) {
+                    return Err(ParseError::new(ParseErrorKind::UnclosedParen, start));
+                }
+                return Err(unexpected(lex, Token::KwRParen.describe(), pstate));
+            }
+            //the parens are part of the expression as far as anyone pointing at it is concerned
+            inner.span = start.to(end);
+            Ok(inner)
+        }
+        Token::OpSub => {
+            let operand = parse_expression(lex, PREFIX_BINDING_POWER, pstate)?;
+            let span = start.to(operand.span);
+            Ok(Node::new(ASTNode::Negate(Box::new(operand)), span))
+        }
+        Token::OpBitNot => {
+            let operand = parse_expression(lex, PREFIX_BINDING_POWER, pstate)?;
+            let span = start.to(operand.span);
+            Ok(Node::new(ASTNode::BitNot(Box::new(operand)), span))
+        }
+        _ => {
+            let bp = either!(stok == Token::KwNot => COMPARISON_BINDING_POWER; PREFIX_BINDING_POWER);
+            let operand = parse_expression(lex, bp, pstate)?;
+            let span = start.to(operand.span);
+            Ok(Node::new(ASTNode::Not(Box::new(operand)), span))
+        }
+    }
+}
+
+//"text {expr:spec} more". the literal bits get their escapes worked out, and each interpolation gets lexed and parsed
+//like any other expression. text without any interpolations in it is plain old Text.
+pub(crate) fn parse_text(text: &str, span: Span, pstate: &mut ParserState) -> Result<Node, ParseError> {
+    //clean the quotes off the ends
+    let inner = &text[1..text.len() - 1];
+    let offset = span.start + 1;
+    let bytes = inner.as_bytes();
+    let mut parts: Vec<Node> = vec![];
+    let mut literal = String::new();
+    //where the literal text we're building up started, and where the bit of it we haven't unescaped yet starts
+    let mut literal_start = 0;
+    let mut chunk = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i = escape_end(inner, i),
+            //{{ and }} are a brace on its own
+            b'{' | b'}' if bytes.get(i + 1) == Some(&bytes[i]) => {
+                literal += &unescape(&inner[chunk..=i], offset + chunk)?;
+                i += 1;
+                chunk = i + 1;
+            }
+            b'{' => {
+                literal += &unescape(&inner[chunk..i], offset + chunk)?;
+                if !literal.is_empty() {
+                    let literal_span = Span::new(offset + literal_start, offset + i);
+                    parts.push(Node::new(ASTNode::Text(std::mem::take(&mut literal)), literal_span));
+                }
+                //the lexer already made sure it gets closed
+                let close = interpolation_end(inner, i).unwrap();
+                parts.push(parse_interpolation(&inner[i + 1..close], offset + i + 1, pstate)?);
+                i = close;
+                chunk = close + 1;
+                literal_start = chunk;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    literal += &unescape(&inner[chunk..], offset + chunk)?;
+    if parts.is_empty() {
+        return Ok(Node::new(ASTNode::Text(literal), span));
+    }
+    if !literal.is_empty() {
+        let literal_span = Span::new(offset + literal_start, offset + inner.len());
+        parts.push(Node::new(ASTNode::Text(literal), literal_span));
+    }
+    Ok(Node::new(ASTNode::Interpolated(parts), span))
+}
+
+//the inside of one {interpolation}: an expression, then maybe a colon and a format spec. `offset` is where it starts.
+pub(crate) fn parse_interpolation(code: &str, offset: usize, pstate: &mut ParserState) -> Result<Node, ParseError> {
+    let (expr, spec) = match format_colon(code) {
+        Some(colon) => (&code[..colon], Some(&code[colon + 1..])),
+        None => (code, None),
+    };
+    let mut tokens = lex_source(expr, offset, &mut pstate.errors);
+    //the expression ends at the `}` (or the colon), so that's what running out of it looks like
+    let end = Span::new(offset + expr.len(), offset + expr.len() + 1);
+    tokens.push(ParsableToken {
+        token: Token::KwRBrace,
+        text: either!(spec.is_some() => ":"; "}").to_string(),
+        span: end,
+        doc: None,
+    });
+    let mut lex = tokens.iter().peekable();
+    let value = parse_nested_expression(&mut lex, pstate)?;
+    if lex.len() > 1 {
+        return Err(unexpected(&mut lex, Token::KwRBrace.describe(), pstate));
+    }
+    match spec {
+        Some(spec) => {
+            let spec = parse_format_spec(spec, end.end)?;
+            let span = value.span;
+            Ok(Node::new(ASTNode::Formatted(Box::new(value), spec), span))
+        }
+        None => Ok(value),
+    }
+}
+
+//where the colon starting a format spec is, if there is one. colons inside brackets or strings belong to the expression.
+pub(crate) fn format_colon(code: &str) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => i += text_end(&code[i + 1..]).ok()?,
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b':' if depth == 0 => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+//[<^>][0][width][.precision]
+pub(crate) fn parse_format_spec(spec: &str, offset: usize) -> Result<FormatSpec, ParseError> {
+    let invalid = || {
+        ParseError::new(
+            ParseErrorKind::InvalidFormatSpec(spec.to_string()),
+            Span::new(offset, offset + spec.len()),
+        )
+    };
+    //how many digits `text` starts with, and what they add up to
+    let number = |text: &str| -> Result<(usize, Option<usize>), ParseError> {
+        let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return Ok((0, None));
+        }
+        text[..digits].parse().map(|n| (digits, Some(n))).map_err(|_| invalid())
+    };
+    let mut format = FormatSpec::default();
+    let mut rest = spec;
+    if let Some(align) = rest.chars().next().filter(|c| matches!(c, '<' | '^' | '>')) {
+        format.align = Some(align);
+        rest = &rest[1..];
+    }
+    if let Some(after) = rest.strip_prefix('0') {
+        format.zero = true;
+        rest = after;
+    }
+    let (digits, width) = number(rest)?;
+    format.width = width.unwrap_or(0);
+    rest = &rest[digits..];
+    if let Some(after) = rest.strip_prefix('.') {
+        let (digits, precision) = number(after)?;
+        format.precision = Some(precision.ok_or_else(invalid)?);
+        rest = &after[digits..];
+    }
+    if !rest.is_empty() {
+        return Err(invalid());
+    }
+    Ok(format)
+}
+
+//( comma separated expressions ), handing back the span of the closing paren
+pub(crate) fn parse_arguments(
+    lex: &mut Peekable<std::slice::Iter<'_, ParsableToken>>,
+    pstate: &mut ParserState,
+) -> Result<(Vec<Node>, Span), ParseError> {
+    let open = expect_token(lex, Token::KwLParen, pstate)?.span;
+    let mut params: Vec<Node> = vec![];
+    loop {
+        match peek_token(lex) {
+            Some(Token::KwRParen) => {
+                let end = peek_span(lex, pstate);
+                lex.next();
+                return Ok((params, end));
+            }
+            None => return Err(ParseError::new(ParseErrorKind::UnclosedParen, open)),
+            Some(_) => {
+                params.push(parse_nested_expression(lex, pstate)?);
+                if peek_token(lex) != Some(Token::KwRParen) {
+                    //running into the end of the statement means the paren never got closed
+                    if matches!(peek_token(lex), Some(Token::KwTerminator) | Some(Token::KwRBrace) | None) {
+                        return Err(ParseError::new(ParseErrorKind::UnclosedParen, open));
+                    }
+                    expect_token(lex, Token::KwComma, pstate)?;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ParserState {
+    //syntax errors we've recovered from so far
+    pub(crate) errors: Vec<ParseError>,
+    //how many loops we're inside of, and whether we're in a function - break/continue/return check these
+    pub(crate) loopDepth: usize,
+    pub(crate) inFunction: bool,
+    //set while parsing an if or loop's head, where `x {` is x followed by the body rather than a struct literal
+    pub(crate) noStructLiterals: bool,
+    //where to point when we run out of tokens
+    pub(crate) eof: Span,
+    pub(crate) debug: bool,
+}
+
+impl ParserState {
+    pub(crate) fn new(debug: bool) -> ParserState {
+        ParserState {
+            loopDepth: 0,
+            inFunction: false,
+            noStructLiterals: false,
+            errors: vec![],
+            eof: Span::default(),
+            debug,
+        }
+    }
+}
+
+//lex and parse a whole file's worth of text. the lexer's complaints go in with the parser's so they all come out together.
+pub(crate) fn parse_source(
+    text: &str,
+    offset: usize,
+    pstate: &mut ParserState,
+) -> Result<Vec<Node>, Vec<ParseError>> {
+    let tokens = lex_source(text, offset, &mut pstate.errors);
+    pstate.eof = Span::new(offset + text.len(), offset + text.len());
+    let mut tok_iter = tokens.iter().peekable();
+    //a string or comment that never closes eats the rest of the file, so anything left open before it only looks unclosed
+    let unterminated = pstate.errors.iter().any(|err| {
+        matches!(
+            err.kind,
+            ParseErrorKind::UnterminatedString | ParseErrorKind::UnterminatedComment
+        )
+    });
+    parse_program(&mut tok_iter, pstate).map_err(|errors| {
+        errors
+            .into_iter()
+            .filter(|err| {
+                !unterminated
+                    || !matches!(
+                        err.kind,
+                        ParseErrorKind::UnexpectedEof { .. }
+                            | ParseErrorKind::UnclosedBrace
+                            | ParseErrorKind::UnclosedParen
+                            | ParseErrorKind::UnclosedBracket
+                    )
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,145 @@
+use crate::ast::Span;
+use crate::error::{ParseError, RuntimeError};
+
+//a loaded script, kept around for turning spans back into something a human can find
+pub struct SourceFile {
+    pub name: String,
+    pub text: String,
+    //where this file starts in its SourceMap
+    pub offset: usize,
+}
+
+impl SourceFile {
+    //1-based line and column of a byte offset into this file
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset.min(self.text.len())];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+
+    //file:line:col, then the offending line with a caret underline beneath the span
+    pub fn render_error(&self, span: Span, message: &str) -> String {
+        let (line, col) = self.line_col(span.start);
+        let start = span.start.min(self.text.len());
+        let line_start = self.text[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = self.text[start..].find('\n').map(|i| start + i).unwrap_or(self.text.len());
+        let line_text = &self.text[line_start..line_end];
+        //we only underline up to the end of the first line, multi-line spans would just be noise
+        let width = self.text[start..span.end.clamp(start, line_end)].chars().count().max(1);
+        let gutter = " ".repeat(line.to_string().len());
+        //tabs stay tabs, so the carets line up under the code however wide the terminal shows them
+        let padding: String = self.text[line_start..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        format!(
+            "\x1b[31merror\x1b[0m: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}\x1b[31m{}\x1b[0m",
+            message,
+            gutter,
+            self.name,
+            line,
+            col,
+            gutter,
+            line,
+            line_text,
+            gutter,
+            padding,
+            "^".repeat(width)
+        )
+    }
+}
+
+//the file itself is a lot of noise in a debug dump of the execution context
+impl std::fmt::Debug for SourceFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SourceFile({})", self.name)
+    }
+}
+
+//every bit of source we've been handed, laid end to end. spans are offsets into the whole lot,
+//so a span on its own is enough to find which file it came from.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    pub files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    //returns where the new file starts, which is what its spans get offset by
+    pub fn add(&mut self, name: String, text: String) -> usize {
+        //the gap of one keeps a span pointing just past the end of a file from landing in the next one
+        let offset = self
+            .files
+            .last()
+            .map(|file| file.offset + file.text.len() + 1)
+            .unwrap_or(0);
+        self.files.push(SourceFile { name, text, offset });
+        offset
+    }
+
+    pub fn file(&self, offset: usize) -> Option<&SourceFile> {
+        self.files.iter().rev().find(|file| file.offset <= offset)
+    }
+
+    //file:line:col
+    pub fn locate(&self, offset: usize) -> String {
+        match self.file(offset) {
+            Some(file) => {
+                let (line, col) = file.line_col(offset - file.offset);
+                format!("{}:{}:{}", file.name, line, col)
+            }
+            None => "<unknown>".to_string(),
+        }
+    }
+
+    pub fn render_error(&self, span: Span, message: &str) -> String {
+        match self.file(span.start) {
+            Some(file) => file.render_error(
+                Span::new(span.start - file.offset, span.end - file.offset),
+                message,
+            ),
+            None => format!("\x1b[31merror\x1b[0m: {}", message),
+        }
+    }
+
+    //the error itself, then which calls we were in when it happened
+    pub fn render_runtime_error(&self, err: &RuntimeError) -> String {
+        let mut out = self.render_error(err.span, &err.to_string());
+        if !err.stack.is_empty() {
+            out += "\nstack trace (most recent call last):";
+            let mut i = 0;
+            while i < err.stack.len() {
+                let frame = &err.stack[i];
+                out += &format!(
+                    "\n  in `{}`, called from {}",
+                    frame.function,
+                    self.locate(frame.call_site.start)
+                );
+                //recursion shows up as the same frame over and over, which only needs saying once
+                let repeats = err.stack[i + 1..]
+                    .iter()
+                    .take_while(|other| *other == frame)
+                    .count();
+                if repeats > 0 {
+                    out += &format!("\n  ... repeated {} more time(s)", repeats);
+                }
+                i += repeats + 1;
+            }
+        }
+        out
+    }
+
+    //every syntax error, in the order they show up in the source
+    pub fn render_parse_errors(&self, mut errors: Vec<ParseError>) -> String {
+        errors.sort_by_key(|e| e.span.start);
+        let mut out = String::new();
+        for err in &errors {
+            out += &self.render_error(err.span, &err.to_string());
+            out += "\n\n";
+        }
+        out += &format!("\x1b[31maborting due to {} syntax error(s)\x1b[0m", errors.len());
+        out
+    }
+}
@@ -0,0 +1,906 @@
+use crate::ast::FormatSpec;
+use crate::builtins::Native;
+use crate::error::RuntimeErrorKind;
+use crate::exec::ScriptFunction;
+use crate::vm::Closure;
+use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+//what expressions evaluate to at runtime. kept apart from ASTNode so the tree only ever describes code.
+#[derive(Clone)]
+pub enum Value {
+    None,
+    //whole number literals are ints, ones with a decimal point are floats
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Boolean(bool),
+    Function(Function),
+    //lists are shared, not copied - handing one to a function (or another variable) hands over the same list
+    List(Rc<RefCell<Vec<Value>>>),
+    //maps are shared the same way. they remember the order their keys went in, which is the order they print in.
+    Map(Rc<RefCell<IndexMap<String, Value>>>),
+    //an instance of a script-declared struct. shared too, which is what lets a method change its self.
+    Struct(Rc<RefCell<Instance>>),
+    //what `import "x.sk" as x` gives you
+    Module(Rc<Module>),
+}
+
+impl Value {
+    /// A new list holding `items`.
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    /// A new map holding `entries`, in the order they come in.
+    pub fn map(entries: impl IntoIterator<Item = (String, Value)>) -> Value {
+        Value::Map(Rc::new(RefCell::new(entries.into_iter().collect())))
+    }
+
+    //what we call each kind of value in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::None => "none",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Text(_) => "text",
+            Value::Boolean(_) => "boolean",
+            Value::Function(_) => "function",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Struct(_) => "struct",
+            Value::Module(_) => "module",
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_value(self, f, &mut vec![])
+    }
+}
+
+//the derived one would go round forever on a list that holds itself, same as Display would
+impl std::fmt::Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::None => write!(f, "None"),
+            Value::Int(num) => write!(f, "Int({})", num),
+            Value::Float(num) => write!(f, "Float({:?})", num),
+            Value::Text(text) => write!(f, "Text({:?})", text),
+            Value::Boolean(b) => write!(f, "Boolean({})", b),
+            Value::Function(function) => write!(f, "{:?}", function),
+            Value::List(_) => write!(f, "List({})", self),
+            Value::Map(_) => write!(f, "Map({})", self),
+            Value::Struct(_) => write!(f, "Struct({})", self),
+            Value::Module(module) => write!(f, "Module({})", module.name),
+        }
+    }
+}
+
+//what a list, map or struct's Rc points at, for telling whether we've seen it before
+fn address<T>(rc: &Rc<T>) -> *const () {
+    Rc::as_ptr(rc) as *const ()
+}
+
+//`seen` holds the lists, maps and structs we're in the middle of printing. one that turns up inside itself prints
+//as [...] (or {...}) rather than forever.
+fn fmt_value(val: &Value, f: &mut std::fmt::Formatter<'_>, seen: &mut Vec<*const ()>) -> std::fmt::Result {
+    match val {
+        Value::None => write!(f, "none"),
+        Value::Int(num) => write!(f, "{}", num),
+        //debug keeps the `.0` on whole floats, so 2.0 doesn't print like the int 2
+        Value::Float(num) => write!(f, "{:?}", num),
+        Value::Text(text) => write!(f, "{}", text),
+        Value::Boolean(b) => write!(f, "{}", b),
+        Value::Function(function) => write!(f, "<fn {}>", function.name()),
+        Value::List(items) if seen.contains(&address(items)) => write!(f, "[...]"),
+        Value::List(items) => {
+            seen.push(address(items));
+            write!(f, "[")?;
+            for (i, item) in items.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                fmt_nested(item, f, seen)?;
+            }
+            seen.pop();
+            write!(f, "]")
+        }
+        Value::Map(entries) if seen.contains(&address(entries)) => write!(f, "{{...}}"),
+        Value::Map(entries) => {
+            seen.push(address(entries));
+            write!(f, "{{")?;
+            for (i, (key, val)) in entries.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{:?}: ", key)?;
+                fmt_nested(val, f, seen)?;
+            }
+            seen.pop();
+            write!(f, "}}")
+        }
+        Value::Struct(instance) if seen.contains(&address(instance)) => {
+            write!(f, "{} {{...}}", instance.borrow().ty.name)
+        }
+        Value::Struct(instance) => {
+            seen.push(address(instance));
+            fmt_instance(&instance.borrow(), f, seen)?;
+            seen.pop();
+            Ok(())
+        }
+        Value::Module(module) => write!(f, "<module {}>", module.name),
+    }
+}
+
+//text gets its quotes back inside a list or map, so ["a, b"] and ["a", "b"] don't look the same
+fn fmt_nested(val: &Value, f: &mut std::fmt::Formatter<'_>, seen: &mut Vec<*const ()>) -> std::fmt::Result {
+    match val {
+        Value::Text(text) => write!(f, "{:?}", text),
+        val => fmt_value(val, f, seen),
+    }
+}
+
+fn fmt_instance(instance: &Instance, f: &mut std::fmt::Formatter<'_>, seen: &mut Vec<*const ()>) -> std::fmt::Result {
+    if instance.fields.is_empty() {
+        return write!(f, "{} {{}}", instance.ty.name);
+    }
+    write!(f, "{} {{ ", instance.ty.name)?;
+    for (i, (name, val)) in instance.ty.fields.iter().zip(&instance.fields).enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: ", name)?;
+        fmt_nested(val, f, seen)?;
+    }
+    write!(f, " }}")
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        values_equal(self, other, &mut vec![])
+    }
+}
+
+//lists, maps and structs are equal when what's in them is. a pair that's already being compared further up counts
+//as equal, so two lists that hold themselves don't go round forever - if nothing else differs, neither do they.
+fn values_equal(a: &Value, b: &Value, seen: &mut Vec<(*const (), *const ())>) -> bool {
+    let pair = match (a, b) {
+        (Value::List(x), Value::List(y)) => (address(x), address(y)),
+        (Value::Map(x), Value::Map(y)) => (address(x), address(y)),
+        (Value::Struct(x), Value::Struct(y)) => (address(x), address(y)),
+        (Value::None, Value::None) => return true,
+        (Value::Int(x), Value::Int(y)) => return x == y,
+        (Value::Float(x), Value::Float(y)) => return x == y,
+        //an int and a float compare as floats, same as they do with `==`
+        (Value::Int(x), Value::Float(y)) | (Value::Float(y), Value::Int(x)) => return *x as f64 == *y,
+        (Value::Text(x), Value::Text(y)) => return x == y,
+        (Value::Boolean(x), Value::Boolean(y)) => return x == y,
+        (Value::Function(x), Value::Function(y)) => return x == y,
+        (Value::Module(x), Value::Module(y)) => return x == y,
+        _ => return false,
+    };
+    if pair.0 == pair.1 || seen.contains(&pair) {
+        return true;
+    }
+    seen.push(pair);
+    let equal = match (a, b) {
+        (Value::List(x), Value::List(y)) => {
+            let (x, y) = (x.borrow(), y.borrow());
+            x.len() == y.len() && x.iter().zip(y.iter()).all(|(a, b)| values_equal(a, b, seen))
+        }
+        //the order the keys went in doesn't matter here, only what they hold
+        (Value::Map(x), Value::Map(y)) => {
+            let (x, y) = (x.borrow(), y.borrow());
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(key, a)| y.get(key).is_some_and(|b| values_equal(a, b, seen)))
+        }
+        (Value::Struct(x), Value::Struct(y)) => instances_equal(&x.borrow(), &y.borrow(), seen),
+        _ => false,
+    };
+    seen.pop();
+    equal
+}
+
+//same struct, same fields. two structs that happen to share a name and fields still aren't the same struct.
+fn instances_equal(a: &Instance, b: &Instance, seen: &mut Vec<(*const (), *const ())>) -> bool {
+    Rc::ptr_eq(&a.ty, &b.ty)
+        && a.fields.len() == b.fields.len()
+        && a.fields.iter().zip(&b.fields).all(|(a, b)| values_equal(a, b, seen))
+}
+
+//a struct as declared. the registry of these lives with whichever engine is running, keyed by struct_key.
+#[derive(Debug)]
+pub(crate) struct StructType {
+    pub(crate) name: String,
+    pub(crate) fields: Vec<String>,
+    //filled in by impl blocks
+    pub(crate) methods: RefCell<HashMap<String, Function>>,
+}
+
+/// One value of a struct type, with its fields in the order the struct declares them.
+pub struct Instance {
+    pub(crate) ty: Rc<StructType>,
+    pub(crate) fields: Vec<Value>,
+}
+
+impl Instance {
+    /// The name of the struct this is one of.
+    pub fn type_name(&self) -> &str {
+        &self.ty.name
+    }
+
+    /// The value of the field called `name`, if the struct has one.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        let position = self.ty.fields.iter().position(|field| field == name)?;
+        self.fields.get(position)
+    }
+
+    /// Every field's name and value, in the order the struct declares them.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.ty.fields.iter().map(|name| name.as_str()).zip(&self.fields)
+    }
+}
+
+impl std::fmt::Display for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_instance(self, f, &mut vec![])
+    }
+}
+
+//the type's methods would drag along every function they've captured
+impl std::fmt::Debug for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Instance) -> bool {
+        instances_equal(self, other, &mut vec![])
+    }
+}
+
+/// Another file, brought in with `import`: whatever its top level had defined by the time it finished running.
+#[derive(Debug)]
+pub struct Module {
+    //the path as the import wrote it
+    pub(crate) name: String,
+    pub(crate) exports: IndexMap<String, Value>,
+}
+
+impl Module {
+    /// The path the import named it by.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What the module's top level defined as `name`, if anything.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.exports.get(name)
+    }
+
+    /// Everything the module's top level defined, sorted by name.
+    pub fn exports(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.exports.iter().map(|(name, val)| (name.as_str(), val))
+    }
+}
+
+//a module only ever gets run once, so there's only ever one of each
+impl PartialEq for Module {
+    fn eq(&self, other: &Module) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// A function, as a value. Which engine made it decides what's inside, so all a host can do is look at it or
+/// hand it back - [`Interpreter::call_function`](crate::Interpreter::call_function) calls functions by name.
+#[derive(Clone)]
+pub struct Function(pub(crate) Rc<Callable>);
+
+pub(crate) enum Callable {
+    Native(Native),
+    //the tree walker's functions: the declaration, and the scope it was declared in
+    Tree(ScriptFunction),
+    //the vm's: the compiled code, and the variables it captured
+    Compiled(Closure),
+}
+
+impl Function {
+    pub(crate) fn new(callable: Callable) -> Function {
+        Function(Rc::new(callable))
+    }
+
+    /// What the function was declared as, or `<anonymous>` for a `fn` expression.
+    pub fn name(&self) -> &str {
+        match &*self.0 {
+            Callable::Native(native) => &native.name,
+            Callable::Tree(function) => &function.name,
+            Callable::Compiled(closure) => &closure.proto.name,
+        }
+    }
+
+    /// The parameter names, for functions that were written in script. Natives don't have any.
+    pub fn params(&self) -> Option<&[String]> {
+        match &*self.0 {
+            Callable::Native(_) => None,
+            Callable::Tree(function) => Some(&function.params),
+            Callable::Compiled(closure) => Some(&closure.proto.params),
+        }
+    }
+
+    /// How many arguments it takes.
+    pub fn arity(&self) -> usize {
+        match &*self.0 {
+            Callable::Native(native) => native.arity,
+            Callable::Tree(function) => function.params.len(),
+            Callable::Compiled(closure) => closure.proto.params.len(),
+        }
+    }
+}
+
+//two functions are only the same if they're the very same value, a copy of the same declaration doesn't count
+impl PartialEq for Function {
+    fn eq(&self, other: &Function) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+//the captured scope would drag every variable along with it
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Function({})", self.name())
+    }
+}
+
+//the binary operators, as far as the runtime is concerned. both the tree walker and the vm go through binary_op
+//so they can't disagree about what `+` means.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Modulo,
+    Pow,
+    FloorDiv,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    EqCheck,
+    NeqCheck,
+    GtCheck,
+    LtCheck,
+    GteCheck,
+    LteCheck,
+}
+
+impl BinOp {
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Modulo => "%",
+            BinOp::Pow => "**",
+            BinOp::FloorDiv => "//",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::ShiftLeft => "<<",
+            BinOp::ShiftRight => ">>",
+            BinOp::EqCheck => "==",
+            BinOp::NeqCheck => "!=",
+            BinOp::GtCheck => ">",
+            BinOp::LtCheck => "<",
+            BinOp::GteCheck => ">=",
+            BinOp::LteCheck => "<=",
+        }
+    }
+
+    //the bitwise operators don't mean anything for floats
+    pub(crate) fn ints_only(self) -> bool {
+        matches!(
+            self,
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::ShiftLeft | BinOp::ShiftRight
+        )
+    }
+}
+
+//the error for an operator that doesn't work on this pair of values
+pub(crate) fn operand_error(op: &str, v1: &Value, v2: &Value) -> RuntimeErrorKind {
+    RuntimeErrorKind::TypeError(format!(
+        "cannot apply `{}` to {} and {}",
+        op,
+        v1.type_name(),
+        v2.type_name()
+    ))
+}
+
+pub(crate) fn binary_op(op: BinOp, v1: Value, v2: Value) -> Result<Value, RuntimeErrorKind> {
+    //two ints is by far the most common case, so it gets to skip the rest
+    if let (Value::Int(n1), Value::Int(n2)) = (&v1, &v2) {
+        return int_op(op, *n1, *n2);
+    }
+    //a float on either side makes it float arithmetic
+    if let (Some(n1), Some(n2)) = (as_float(&v1), as_float(&v2)) {
+        if op.ints_only() {
+            return Err(operand_error(op.symbol(), &v1, &v2));
+        }
+        return float_op(op, n1, n2);
+    }
+    match op {
+        BinOp::Add => add_values(v1, v2),
+        //values of different types are never equal, no need to complain about it
+        BinOp::EqCheck => Ok(Value::Boolean(v1 == v2)),
+        BinOp::NeqCheck => Ok(Value::Boolean(v1 != v2)),
+        //everything else only makes sense for numbers
+        _ => Err(operand_error(op.symbol(), &v1, &v2)),
+    }
+}
+
+//an int or a float as a float, for mixed arithmetic. anything else isn't a number at all.
+pub(crate) fn as_float(val: &Value) -> Option<f64> {
+    match val {
+        Value::Int(n) => Some(*n as f64),
+        Value::Float(n) => Some(*n),
+        _ => None,
+    }
+}
+
+//ints stay ints: division rounds towards zero, and going past what an i64 can hold is an error rather than a wrap
+pub(crate) fn int_op(op: BinOp, n1: i64, n2: i64) -> Result<Value, RuntimeErrorKind> {
+    let overflow = || RuntimeErrorKind::IntegerOverflow(op.symbol().to_string());
+    match op {
+        BinOp::Add => n1.checked_add(n2).map(Value::Int).ok_or_else(overflow),
+        BinOp::Sub => n1.checked_sub(n2).map(Value::Int).ok_or_else(overflow),
+        BinOp::Mul => n1.checked_mul(n2).map(Value::Int).ok_or_else(overflow),
+        BinOp::Div | BinOp::FloorDiv | BinOp::Modulo if n2 == 0 => Err(RuntimeErrorKind::DivisionByZero),
+        BinOp::Div => n1.checked_div(n2).map(Value::Int).ok_or_else(overflow),
+        //the same as / when the signs match, one lower when they don't and it doesn't go in evenly
+        BinOp::FloorDiv => {
+            let quotient = n1.checked_div(n2).ok_or_else(overflow)?;
+            let inexact = n1 % n2 != 0 && (n1 < 0) != (n2 < 0);
+            Ok(Value::Int(if inexact { quotient - 1 } else { quotient }))
+        }
+        BinOp::Modulo => n1.checked_rem(n2).map(Value::Int).ok_or_else(overflow),
+        //an int can't hold 2 ** -1, so a negative power makes it float arithmetic
+        BinOp::Pow if n2 < 0 => float_op(op, n1 as f64, n2 as f64),
+        BinOp::Pow => u32::try_from(n2)
+            .ok()
+            .and_then(|n2| n1.checked_pow(n2))
+            .map(Value::Int)
+            .ok_or_else(overflow),
+        BinOp::BitAnd => Ok(Value::Int(n1 & n2)),
+        BinOp::BitOr => Ok(Value::Int(n1 | n2)),
+        BinOp::BitXor => Ok(Value::Int(n1 ^ n2)),
+        BinOp::ShiftLeft | BinOp::ShiftRight if n2 < 0 => Err(RuntimeErrorKind::TypeError(format!(
+            "cannot shift by a negative amount ({})",
+            n2
+        ))),
+        //shifting bits off the top is an overflow, same as it would be multiplying by 2 that many times
+        BinOp::ShiftLeft if n1 == 0 => Ok(Value::Int(0)),
+        BinOp::ShiftLeft => match n2 {
+            0..=63 if (n1 << n2) >> n2 == n1 => Ok(Value::Int(n1 << n2)),
+            _ => Err(overflow()),
+        },
+        //shifting right far enough leaves nothing but the sign
+        BinOp::ShiftRight => Ok(Value::Int(n1 >> n2.min(63))),
+        BinOp::EqCheck => Ok(Value::Boolean(n1 == n2)),
+        BinOp::NeqCheck => Ok(Value::Boolean(n1 != n2)),
+        BinOp::GtCheck => Ok(Value::Boolean(n1 > n2)),
+        BinOp::LtCheck => Ok(Value::Boolean(n1 < n2)),
+        BinOp::GteCheck => Ok(Value::Boolean(n1 >= n2)),
+        BinOp::LteCheck => Ok(Value::Boolean(n1 <= n2)),
+    }
+}
+
+pub(crate) fn float_op(op: BinOp, n1: f64, n2: f64) -> Result<Value, RuntimeErrorKind> {
+    match op {
+        BinOp::Add => Ok(Value::Float(n1 + n2)),
+        BinOp::Sub => Ok(Value::Float(n1 - n2)),
+        BinOp::Mul => Ok(Value::Float(n1 * n2)),
+        BinOp::Div | BinOp::FloorDiv | BinOp::Modulo if n2 == 0.0 => Err(RuntimeErrorKind::DivisionByZero),
+        BinOp::Div => Ok(Value::Float(n1 / n2)),
+        BinOp::Modulo => Ok(Value::Float(n1 % n2)),
+        BinOp::Pow => Ok(Value::Float(n1.powf(n2))),
+        BinOp::FloorDiv => Ok(Value::Float((n1 / n2).floor())),
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::ShiftLeft | BinOp::ShiftRight => {
+            Err(operand_error(op.symbol(), &Value::Float(n1), &Value::Float(n2)))
+        }
+        BinOp::EqCheck => Ok(Value::Boolean(n1 == n2)),
+        BinOp::NeqCheck => Ok(Value::Boolean(n1 != n2)),
+        BinOp::GtCheck => Ok(Value::Boolean(n1 > n2)),
+        BinOp::LtCheck => Ok(Value::Boolean(n1 < n2)),
+        BinOp::GteCheck => Ok(Value::Boolean(n1 >= n2)),
+        BinOp::LteCheck => Ok(Value::Boolean(n1 <= n2)),
+    }
+}
+
+//numbers add, text glues anything printable onto itself, and lists join up into a new list
+pub(crate) fn add_values(v1: Value, v2: Value) -> Result<Value, RuntimeErrorKind> {
+    match (v1, v2) {
+        (Value::List(l1), Value::List(l2)) => {
+            let joined = l1.borrow().iter().chain(l2.borrow().iter()).cloned().collect();
+            Ok(Value::list(joined))
+        }
+        (Value::Text(t1), v2) if !matches!(v2, Value::None) => {
+            Ok(Value::Text(format!("{}{}", t1, v2)))
+        }
+        (v1, Value::Text(t2)) if !matches!(v1, Value::None) => {
+            Ok(Value::Text(format!("{}{}", v1, t2)))
+        }
+        (v1, v2) => Err(operand_error("+", &v1, &v2)),
+    }
+}
+
+//"{x:>8.2}" - x as text, padded out and rounded off the way the spec says
+pub(crate) fn format_value(val: &Value, spec: &FormatSpec) -> Result<String, RuntimeErrorKind> {
+    let number = matches!(val, Value::Int(_) | Value::Float(_));
+    let text = match (val, spec.precision) {
+        (val, None) => val.to_string(),
+        (Value::Int(n), Some(digits)) => format!("{:.*}", digits, *n as f64),
+        (Value::Float(n), Some(digits)) => format!("{:.*}", digits, n),
+        (Value::Text(text), Some(chars)) => text.chars().take(chars).collect(),
+        (val, Some(_)) => {
+            return Err(RuntimeErrorKind::TypeError(format!(
+                "cannot give {} a precision",
+                val.type_name()
+            )))
+        }
+    };
+    let pad = spec.width.saturating_sub(text.chars().count());
+    if pad == 0 {
+        return Ok(text);
+    }
+    if spec.zero && number {
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text.as_str()),
+        };
+        return Ok(format!("{}{}{}", sign, "0".repeat(pad), digits));
+    }
+    Ok(match spec.align.unwrap_or(if number { '>' } else { '<' }) {
+        '>' => format!("{}{}", " ".repeat(pad), text),
+        '^' => format!("{}{}{}", " ".repeat(pad / 2), text, " ".repeat(pad - pad / 2)),
+        _ => format!("{}{}", text, " ".repeat(pad)),
+    })
+}
+
+pub(crate) fn int_operands(op: &str, v1: &Value, v2: &Value) -> Result<(i64, i64), RuntimeErrorKind> {
+    match (v1, v2) {
+        (Value::Int(n1), Value::Int(n2)) => Ok((*n1, *n2)),
+        _ => Err(operand_error(op, v1, v2)),
+    }
+}
+
+pub(crate) fn negate_value(val: Value) -> Result<Value, RuntimeErrorKind> {
+    match val {
+        Value::Int(n) => n
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or_else(|| RuntimeErrorKind::IntegerOverflow("-".to_string())),
+        Value::Float(n) => Ok(Value::Float(-n)),
+        val => Err(RuntimeErrorKind::TypeError(format!(
+            "cannot negate {}",
+            val.type_name()
+        ))),
+    }
+}
+
+pub(crate) fn bit_not_value(val: Value) -> Result<Value, RuntimeErrorKind> {
+    match val {
+        Value::Int(n) => Ok(Value::Int(!n)),
+        val => Err(RuntimeErrorKind::TypeError(format!(
+            "cannot apply `~` to {}",
+            val.type_name()
+        ))),
+    }
+}
+
+pub(crate) fn not_value(val: &Value) -> Value {
+    Value::Boolean(!is_truthy(val))
+}
+
+//= Lists
+
+//an index has to be an int. negative ones count back from the end.
+pub(crate) fn whole_index(index: &Value) -> Result<i64, RuntimeErrorKind> {
+    match index {
+        Value::Int(n) => Ok(*n),
+        val => Err(RuntimeErrorKind::TypeError(format!(
+            "cannot index a list with {}",
+            val.type_name()
+        ))),
+    }
+}
+
+//where `index` lands in a list `length` long, if it lands anywhere
+pub(crate) fn list_position(index: &Value, length: usize) -> Result<usize, RuntimeErrorKind> {
+    let index = whole_index(index)?;
+    let position = if index < 0 { index + length as i64 } else { index };
+    if position < 0 || position >= length as i64 {
+        return Err(RuntimeErrorKind::IndexOutOfBounds { index, length });
+    }
+    Ok(position as usize)
+}
+
+//xs[i] or m[key]
+pub(crate) fn index_value(list: &Value, index: &Value) -> Result<Value, RuntimeErrorKind> {
+    match list {
+        Value::List(items) => {
+            let items = items.borrow();
+            let position = list_position(index, items.len())?;
+            Ok(items[position].clone())
+        }
+        Value::Map(entries) => {
+            let key = map_key(index)?;
+            let found = entries.borrow().get(key).cloned();
+            found.ok_or_else(|| RuntimeErrorKind::MissingKey(key.to_string()))
+        }
+        val => Err(RuntimeErrorKind::TypeError(format!("cannot index {}", val.type_name()))),
+    }
+}
+
+//xs[i] = v, or m[key] = v, which adds the key if it isn't there yet
+pub(crate) fn set_index(list: &Value, index: &Value, val: Value) -> Result<(), RuntimeErrorKind> {
+    match list {
+        Value::List(items) => {
+            let mut items = items.borrow_mut();
+            let position = list_position(index, items.len())?;
+            items[position] = val;
+            Ok(())
+        }
+        Value::Map(entries) => {
+            let key = map_key(index)?;
+            entries.borrow_mut().insert(key.to_string(), val);
+            Ok(())
+        }
+        val => Err(RuntimeErrorKind::TypeError(format!("cannot index {}", val.type_name()))),
+    }
+}
+
+//xs[from..to], as a new list. like python, bounds past either end just stop at the end, and none means the end.
+pub(crate) fn slice_value(list: &Value, from: &Value, to: &Value) -> Result<Value, RuntimeErrorKind> {
+    let items = match list {
+        Value::List(items) => items.borrow(),
+        val => return Err(RuntimeErrorKind::TypeError(format!("cannot slice {}", val.type_name()))),
+    };
+    let length = items.len() as i64;
+    let bound = |val: &Value, default: i64| -> Result<usize, RuntimeErrorKind> {
+        let index = match val {
+            Value::None => default,
+            val => whole_index(val)?,
+        };
+        let position = if index < 0 { index + length } else { index };
+        Ok(position.clamp(0, length) as usize)
+    };
+    let (from, to) = (bound(from, 0)?, bound(to, length)?);
+    Ok(Value::list(items[from..to.max(from)].to_vec()))
+}
+
+//= Maps
+
+pub(crate) fn map_key(key: &Value) -> Result<&str, RuntimeErrorKind> {
+    match key {
+        Value::Text(key) => Ok(key),
+        val => Err(RuntimeErrorKind::TypeError(format!(
+            "map keys have to be text, not {}",
+            val.type_name()
+        ))),
+    }
+}
+
+//m.key, which is just m["key"] with less typing - or a struct's field
+pub(crate) fn get_field(val: &Value, name: &str) -> Result<Value, RuntimeErrorKind> {
+    match val {
+        Value::Map(entries) => {
+            let found = entries.borrow().get(name).cloned();
+            found.ok_or_else(|| RuntimeErrorKind::MissingKey(name.to_string()))
+        }
+        Value::Struct(instance) => {
+            let instance = instance.borrow();
+            let position = field_position(&instance.ty, name)?;
+            Ok(instance.fields[position].clone())
+        }
+        Value::Module(module) => {
+            let found = module.exports.get(name).cloned();
+            found.ok_or_else(|| RuntimeErrorKind::NoSuchExport {
+                module: module.name.clone(),
+                name: name.to_string(),
+            })
+        }
+        val => Err(RuntimeErrorKind::TypeError(format!(
+            "{} has no fields",
+            val.type_name()
+        ))),
+    }
+}
+
+pub(crate) fn set_field(target: &Value, name: &str, val: Value) -> Result<(), RuntimeErrorKind> {
+    match target {
+        Value::Map(entries) => {
+            entries.borrow_mut().insert(name.to_string(), val);
+            Ok(())
+        }
+        //unlike a map, a struct can't pick up new fields along the way
+        Value::Struct(instance) => {
+            let mut instance = instance.borrow_mut();
+            let position = field_position(&instance.ty, name)?;
+            instance.fields[position] = val;
+            Ok(())
+        }
+        target => Err(RuntimeErrorKind::TypeError(format!(
+            "{} has no fields",
+            target.type_name()
+        ))),
+    }
+}
+
+//= Structs
+
+//what a struct is filed under in the registry. a module's structs go under its path, so they don't trip over
+//the importer's (or another module's) structs of the same name.
+pub(crate) fn struct_key(module: Option<&str>, name: &str) -> String {
+    match module {
+        Some(path) => format!("{}::{}", path, name),
+        None => name.to_string(),
+    }
+}
+
+//what the script called the struct filed under `key`
+fn struct_name(key: &str) -> &str {
+    key.rsplit("::").next().unwrap_or(key)
+}
+
+pub(crate) fn field_position(ty: &StructType, name: &str) -> Result<usize, RuntimeErrorKind> {
+    match ty.fields.iter().position(|field| field == name) {
+        Some(position) => Ok(position),
+        None => Err(RuntimeErrorKind::NoSuchField {
+            ty: ty.name.clone(),
+            field: name.to_string(),
+        }),
+    }
+}
+
+//Point { y: 2, x: 1 }. the fields can come in any order, but every one of them has to be there.
+pub(crate) fn make_instance(
+    structs: &HashMap<String, Rc<StructType>>,
+    key: &str,
+    names: &[String],
+    vals: Vec<Value>,
+) -> Result<Value, RuntimeErrorKind> {
+    let ty = match structs.get(key) {
+        Some(ty) => ty.clone(),
+        None => return Err(RuntimeErrorKind::UndefinedStruct(struct_name(key).to_string())),
+    };
+    let mut fields = vec![None; ty.fields.len()];
+    for (field, val) in names.iter().zip(vals) {
+        fields[field_position(&ty, field)?] = Some(val);
+    }
+    let mut instance = Instance {
+        ty: ty.clone(),
+        fields: Vec::with_capacity(fields.len()),
+    };
+    for (field, val) in ty.fields.iter().zip(fields) {
+        match val {
+            Some(val) => instance.fields.push(val),
+            None => {
+                return Err(RuntimeErrorKind::MissingField {
+                    ty: ty.name.clone(),
+                    field: field.clone(),
+                })
+            }
+        }
+    }
+    Ok(Value::Struct(Rc::new(RefCell::new(instance))))
+}
+
+//declaring a struct again starts it over, methods and all
+pub(crate) fn declare_struct(structs: &mut HashMap<String, Rc<StructType>>, key: &str, fields: &[String]) {
+    let ty = StructType {
+        name: struct_name(key).to_string(),
+        fields: fields.to_vec(),
+        methods: RefCell::default(),
+    };
+    structs.insert(key.to_string(), Rc::new(ty));
+}
+
+pub(crate) fn define_method(
+    structs: &HashMap<String, Rc<StructType>>,
+    key: &str,
+    name: &str,
+    method: Value,
+) -> Result<(), RuntimeErrorKind> {
+    let ty = match structs.get(key) {
+        Some(ty) => ty,
+        None => return Err(RuntimeErrorKind::UndefinedStruct(struct_name(key).to_string())),
+    };
+    if let Value::Function(method) = method {
+        ty.methods.borrow_mut().insert(name.to_string(), method);
+    }
+    Ok(())
+}
+
+//what thing.name(...) calls, and whether thing gets handed to it as self. a struct's methods come first, then any
+//function sitting in one of its fields. a map's functions are just called, since there's no self to speak of.
+pub(crate) fn find_method(target: &Value, name: &str) -> Result<(Value, bool), RuntimeErrorKind> {
+    match target {
+        Value::Struct(instance) => {
+            let instance = instance.borrow();
+            if let Some(method) = instance.ty.methods.borrow().get(name) {
+                return Ok((Value::Function(method.clone()), true));
+            }
+            match instance.ty.fields.iter().position(|field| field == name) {
+                Some(position) => Ok((instance.fields[position].clone(), false)),
+                None => Err(RuntimeErrorKind::NoSuchMethod {
+                    ty: instance.ty.name.clone(),
+                    method: name.to_string(),
+                }),
+            }
+        }
+        Value::Map(_) | Value::Module(_) => Ok((get_field(target, name)?, false)),
+        val => Err(RuntimeErrorKind::TypeError(format!(
+            "{} has no methods",
+            val.type_name()
+        ))),
+    }
+}
+
+//= Looping
+
+//what `loop x in` goes through. it gets its own copy up front, so changing the list (or map) from the body doesn't
+//change what the loop sees.
+pub(crate) fn loop_copy(val: &Value) -> Result<Value, RuntimeErrorKind> {
+    match val {
+        Value::List(items) => Ok(Value::list(items.borrow().clone())),
+        Value::Map(entries) => Ok(Value::Map(Rc::new(RefCell::new(entries.borrow().clone())))),
+        val => Err(RuntimeErrorKind::TypeError(format!(
+            "cannot loop over {}",
+            val.type_name()
+        ))),
+    }
+}
+
+//the loop variables for the entry at `position` in a loop_copy, or None once we're past the end.
+//`loop x in` gets a list's items and a map's keys, `loop k, v in` gets index and item or key and value.
+pub(crate) fn loop_entry(copy: &Value, position: usize, pair: bool) -> Option<(Value, Value)> {
+    match copy {
+        Value::List(items) => {
+            let item = items.borrow().get(position).cloned()?;
+            if pair {
+                Some((Value::Int(position as i64), item))
+            } else {
+                Some((item, Value::None))
+            }
+        }
+        Value::Map(entries) => {
+            let entries = entries.borrow();
+            let (key, val) = entries.get_index(position)?;
+            Some((Value::Text(key.clone()), val.clone()))
+        }
+        _ => None,
+    }
+}
+
+//what counts as true for if, `loop while`, and/or and not. none, false, zero and anything empty are false,
+//everything else is true.
+pub(crate) fn is_truthy(val: &Value) -> bool {
+    match val {
+        Value::None => false,
+        Value::Boolean(b) => *b,
+        Value::Int(num) => *num != 0,
+        Value::Float(num) => *num != 0.0,
+        Value::Text(text) => !text.is_empty(),
+        Value::List(items) => !items.borrow().is_empty(),
+        Value::Map(entries) => !entries.borrow().is_empty(),
+        Value::Function(_) | Value::Struct(_) | Value::Module(_) => true,
+    }
+}
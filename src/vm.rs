@@ -0,0 +1,577 @@
+use crate::ast::Span;
+use crate::builtins::call_native;
+use crate::compiler::{CompilerState, Op, Proto};
+use crate::error::{RuntimeError, RuntimeErrorKind, StackFrame, MAX_CALL_DEPTH};
+use crate::value::{
+    binary_op, bit_not_value, declare_struct, define_method, find_method, format_value, get_field, index_value, int_op,
+    int_operands, is_truthy, loop_copy, loop_entry, make_instance, map_key, negate_value, not_value, set_field,
+    set_index, slice_value, Callable, Function, StructType, Value,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+//a variable a closure has captured. it stays pointing at the stack slot for as long as the function that owns it
+//is running (so both sides see each other's changes), and gets moved in with the closure once it isn't.
+#[derive(Debug)]
+pub(crate) enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+pub(crate) type Upvalues = Rc<[Rc<RefCell<Upvalue>>]>;
+
+//the vm's functions: some compiled code, and the variables it's captured
+#[derive(Debug)]
+pub(crate) struct Closure {
+    pub(crate) proto: Rc<Proto>,
+    pub(crate) upvalues: Upvalues,
+}
+
+#[derive(Debug)]
+pub(crate) struct CallFrame {
+    pub(crate) proto: Rc<Proto>,
+    pub(crate) upvalues: Upvalues,
+    //where to pick back up once whatever we called returns
+    pub(crate) ip: usize,
+    //where our locals start on the stack
+    pub(crate) base: usize,
+    //where we got called from, for tracebacks. the script itself wasn't called from anywhere.
+    pub(crate) call_site: Option<Span>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Vm {
+    pub(crate) stack: Vec<Value>,
+    pub(crate) frames: Vec<CallFrame>,
+    //indexed by the slots in the CompilerState. None until the let/fn that defines it has run.
+    pub(crate) globals: Vec<Option<Value>>,
+    //the upvalues still pointing into the stack, sorted by slot, so returning can close everything past a point
+    pub(crate) openUpvalues: Vec<Rc<RefCell<Upvalue>>>,
+    //every struct declared so far, by name
+    pub(crate) structs: HashMap<String, Rc<StructType>>,
+}
+
+impl Vm {
+    //build an error that remembers which calls we were in when it happened
+    #[cold]
+    pub(crate) fn error(&self, kind: RuntimeErrorKind, span: Span) -> RuntimeError {
+        RuntimeError {
+            kind,
+            span,
+            stack: self
+                .frames
+                .iter()
+                .filter_map(|frame| {
+                    frame.call_site.map(|call_site| StackFrame {
+                        function: frame.proto.name.clone(),
+                        call_site,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    //run a compiled script to completion. on an error the stack and frames get put back how they were.
+    pub(crate) fn run(&mut self, script: Rc<Proto>, cstate: &CompilerState) -> Result<Value, RuntimeError> {
+        self.enter(Value::None, script, Rc::new([]), vec![], cstate)
+    }
+
+    //call a function for the host. it wasn't called from anywhere in the script, so it doesn't get a call site
+    //(or a line in tracebacks). the arity's already been checked.
+    pub(crate) fn call_value(
+        &mut self,
+        function: &Function,
+        args: Vec<Value>,
+        cstate: &CompilerState,
+    ) -> Result<Value, RuntimeError> {
+        match &*function.0 {
+            Callable::Compiled(closure) => self.enter(
+                Value::Function(function.clone()),
+                closure.proto.clone(),
+                closure.upvalues.clone(),
+                args,
+                cstate,
+            ),
+            Callable::Native(native) => call_native(native, &args).map_err(|kind| self.error(kind, Span::default())),
+            Callable::Tree(_) => Err(self.error(
+                RuntimeErrorKind::TypeError("cannot call a function from a different interpreter".to_string()),
+                Span::default(),
+            )),
+        }
+    }
+
+    pub(crate) fn enter(
+        &mut self,
+        callee: Value,
+        proto: Rc<Proto>,
+        upvalues: Upvalues,
+        args: Vec<Value>,
+        cstate: &CompilerState,
+    ) -> Result<Value, RuntimeError> {
+        self.globals.resize(cstate.globalNames.len(), None);
+        let depth = self.frames.len();
+        self.stack.push(callee);
+        let base = self.stack.len();
+        self.stack.extend(args);
+        self.stack.resize(base + proto.locals, Value::None);
+        self.frames.push(CallFrame {
+            proto,
+            upvalues,
+            ip: 0,
+            base,
+            call_site: None,
+        });
+        let result = self.execute(depth, cstate);
+        if result.is_err() {
+            self.close_upvalues(base);
+            self.frames.truncate(depth);
+            self.stack.truncate(base - 1);
+        }
+        result
+    }
+
+    //the upvalue for a stack slot, shared with any other closure that's already captured it
+    pub(crate) fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let at = self
+            .openUpvalues
+            .partition_point(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(open) if open < slot));
+        if let Some(upvalue) = self.openUpvalues.get(at) {
+            if matches!(*upvalue.borrow(), Upvalue::Open(open) if open == slot) {
+                return upvalue.clone();
+            }
+        }
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.openUpvalues.insert(at, upvalue.clone());
+        upvalue
+    }
+
+    //the stack from `from` up is going away, so move what's there into the upvalues pointing at it
+    pub(crate) fn close_upvalues(&mut self, from: usize) {
+        let at = self
+            .openUpvalues
+            .partition_point(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(open) if open < from));
+        for upvalue in self.openUpvalues.drain(at..) {
+            let mut upvalue = upvalue.borrow_mut();
+            if let Upvalue::Open(slot) = *upvalue {
+                *upvalue = Upvalue::Closed(self.stack[slot].clone());
+            }
+        }
+    }
+
+    //the main loop. returns once the frame that was on top when we started returns.
+    pub(crate) fn execute(&mut self, depth: usize, cstate: &CompilerState) -> Result<Value, RuntimeError> {
+        //the current frame is kept in locals, the copy in self.frames only gets updated when we make a call
+        let frame = &self.frames[self.frames.len() - 1];
+        let mut proto = frame.proto.clone();
+        let mut upvalues = frame.upvalues.clone();
+        let mut ip = frame.ip;
+        let mut base = frame.base;
+        //bail out with an error pointing at the instruction we're on
+        macro_rules! fail {
+            ($kind:expr) => {
+                return Err(self.error($kind, proto.spans[ip - 1]))
+            };
+        }
+        //same, for the helpers that hand back a Result
+        macro_rules! push_result {
+            ($result:expr) => {
+                match $result {
+                    Ok(val) => self.stack.push(val),
+                    Err(kind) => fail!(kind),
+                }
+            };
+        }
+        //call whatever's sitting under the top `argc` values on the stack with them as its arguments
+        macro_rules! call {
+            ($argc:expr) => {{
+                let argc: usize = $argc;
+                let at = self.stack.len() - argc - 1;
+                let callee = match &self.stack[at] {
+                    Value::Function(function) => match &*function.0 {
+                        Callable::Compiled(closure) => closure,
+                        //natives read their arguments right off the stack
+                        Callable::Native(native) => {
+                            let result = call_native(native, &self.stack[at + 1..]);
+                            self.stack.truncate(at);
+                            push_result!(result);
+                            continue;
+                        }
+                        Callable::Tree(_) => fail!(RuntimeErrorKind::TypeError(
+                            "cannot call a function from a different interpreter".to_string()
+                        )),
+                    },
+                    val => fail!(RuntimeErrorKind::TypeError(format!(
+                        "cannot call {}",
+                        val.type_name()
+                    ))),
+                };
+                if callee.proto.params.len() != argc {
+                    fail!(RuntimeErrorKind::ArityMismatch {
+                        function: callee.proto.name.clone(),
+                        expected: callee.proto.params.len(),
+                        found: argc,
+                    });
+                }
+                //the script's own frame doesn't count
+                if self.frames.len() > MAX_CALL_DEPTH {
+                    fail!(RuntimeErrorKind::StackOverflow);
+                }
+                let callee_proto = callee.proto.clone();
+                let callee_upvalues = callee.upvalues.clone();
+                //the arguments are already sitting where the callee's first locals go
+                let call_site = proto.spans[ip - 1];
+                let caller = self.frames.len() - 1;
+                self.frames[caller].ip = ip;
+                base = at + 1;
+                self.stack.resize(base + callee_proto.locals, Value::None);
+                self.frames.push(CallFrame {
+                    proto: callee_proto.clone(),
+                    upvalues: callee_upvalues.clone(),
+                    ip: 0,
+                    base,
+                    call_site: Some(call_site),
+                });
+                proto = callee_proto;
+                upvalues = callee_upvalues;
+                ip = 0;
+            }};
+        }
+        loop {
+            let op = proto.code[ip];
+            ip += 1;
+            match op {
+                Op::Constant(index) => {
+                    let val = match proto.constants[index as usize] {
+                        Value::Int(n) => Value::Int(n),
+                        ref val => val.clone(),
+                    };
+                    self.stack.push(val);
+                }
+                Op::Pop => {
+                    self.stack.pop();
+                }
+                //ints are copied straight across, only everything else goes through clone
+                Op::GetLocal(slot) => {
+                    let val = match self.stack[base + slot as usize] {
+                        Value::Int(n) => Value::Int(n),
+                        ref val => val.clone(),
+                    };
+                    self.stack.push(val);
+                }
+                Op::SetLocal(slot) => {
+                    let val = self.pop();
+                    self.stack[base + slot as usize] = val;
+                }
+                Op::GetGlobal(slot) => {
+                    let val = match self.globals[slot as usize] {
+                        Some(Value::Int(n)) => Value::Int(n),
+                        Some(ref val) => val.clone(),
+                        None => fail!(RuntimeErrorKind::UndefinedVariable(cstate.global_name(slot))),
+                    };
+                    self.stack.push(val);
+                }
+                Op::DefineGlobal(slot) => {
+                    self.globals[slot as usize] = Some(self.pop());
+                }
+                //the value moves off the stack into the slot. an int over an int is just written in place
+                Op::SetGlobal(slot) => match (&mut self.globals[slot as usize], self.stack.pop()) {
+                    (Some(Value::Int(global)), Some(Value::Int(n))) => *global = n,
+                    (Some(global), val) => *global = val.unwrap_or(Value::None),
+                    (None, _) => fail!(RuntimeErrorKind::UndefinedVariable(cstate.global_name(slot))),
+                },
+                Op::Binary(binop) => {
+                    //ints get worked out right where they sit on the stack
+                    let len = self.stack.len();
+                    if let [Value::Int(ref mut n1), Value::Int(n2)] = self.stack[len - 2..] {
+                        match int_op(binop, *n1, n2) {
+                            Ok(Value::Int(n)) => *n1 = n,
+                            Ok(val) => self.stack[len - 2] = val,
+                            Err(kind) => fail!(kind),
+                        }
+                        self.stack.pop();
+                    } else {
+                        let v2 = self.pop();
+                        let v1 = self.pop();
+                        push_result!(binary_op(binop, v1, v2));
+                    }
+                }
+                Op::Dup(count) => {
+                    let from = self.stack.len() - count as usize;
+                    self.stack.extend_from_within(from..);
+                }
+                Op::Negate => {
+                    let val = self.pop();
+                    push_result!(negate_value(val));
+                }
+                Op::BitNot => {
+                    let val = self.pop();
+                    push_result!(bit_not_value(val));
+                }
+                Op::Not => {
+                    let val = self.pop();
+                    self.stack.push(not_value(&val));
+                }
+                Op::Jump(to) => ip = to as usize,
+                Op::JumpIfFalsy(to) => {
+                    if !is_truthy(&self.pop()) {
+                        ip = to as usize;
+                    }
+                }
+                Op::JumpIfTruthy(to) => {
+                    if is_truthy(&self.pop()) {
+                        ip = to as usize;
+                    }
+                }
+                Op::JumpIfFalsyOrPop(to) => {
+                    if is_truthy(self.stack.last().unwrap()) {
+                        self.stack.pop();
+                    } else {
+                        ip = to as usize;
+                    }
+                }
+                Op::JumpIfTruthyOrPop(to) => {
+                    if is_truthy(self.stack.last().unwrap()) {
+                        ip = to as usize;
+                    } else {
+                        self.stack.pop();
+                    }
+                }
+                Op::CompareJump {
+                    op: binop,
+                    when,
+                    to,
+                } => {
+                    let len = self.stack.len();
+                    let result =
+                        if let [Value::Int(n1), Value::Int(n2)] = self.stack[len - 2..] {
+                            self.stack.truncate(len - 2);
+                            int_op(binop, n1, n2)
+                        } else {
+                            let v2 = self.pop();
+                            let v1 = self.pop();
+                            binary_op(binop, v1, v2)
+                        };
+                    match result {
+                        Ok(Value::Boolean(b)) if b == when => ip = to as usize,
+                        Ok(_) => {}
+                        Err(kind) => fail!(kind),
+                    }
+                }
+                Op::RangeInit(counter) => {
+                    let upperbound = self.pop();
+                    let lowerbound = self.pop();
+                    let (lower, upper) = match int_operands("in .. to", &lowerbound, &upperbound) {
+                        Ok(bounds) => bounds,
+                        Err(kind) => fail!(kind),
+                    };
+                    let slot = base + counter as usize;
+                    self.stack[slot] = Value::Int(lower);
+                    self.stack[slot + 1] = Value::Int(upper);
+                }
+                Op::RangeNext { counter, var, body } => {
+                    let slot = base + counter as usize;
+                    if let [Value::Int(ref mut i), Value::Int(end)] = self.stack[slot..slot + 2] {
+                        if *i < end {
+                            let at = *i;
+                            *i += 1;
+                            self.stack[base + var as usize] = Value::Int(at);
+                            ip = body as usize;
+                        }
+                    }
+                }
+                Op::EachInit(items) => {
+                    let list = self.pop();
+                    let slot = base + items as usize;
+                    match loop_copy(&list) {
+                        Ok(copy) => self.stack[slot] = copy,
+                        Err(kind) => fail!(kind),
+                    }
+                    self.stack[slot + 1] = Value::Int(0);
+                }
+                Op::EachNext { items, var, pair, body } => {
+                    let slot = base + items as usize;
+                    if let Value::Int(position) = self.stack[slot + 1] {
+                        let position = position as usize;
+                        if let Some((first, second)) = loop_entry(&self.stack[slot], position, pair) {
+                            self.stack[base + var as usize] = first;
+                            self.stack[base + var as usize + 1] = second;
+                            self.stack[slot + 1] = Value::Int(position as i64 + 1);
+                            ip = body as usize;
+                        }
+                    }
+                }
+                Op::Concat(count) => {
+                    let parts = self.stack.split_off(self.stack.len() - count as usize);
+                    let text: String = parts.iter().map(|part| part.to_string()).collect();
+                    self.stack.push(Value::Text(text));
+                }
+                Op::Format(index) => {
+                    let val = self.pop();
+                    push_result!(format_value(&val, &proto.formats[index as usize]).map(Value::Text));
+                }
+                Op::MakeList(count) => {
+                    let items = self.stack.split_off(self.stack.len() - count as usize);
+                    self.stack.push(Value::list(items));
+                }
+                Op::MapKey => {
+                    if let Err(kind) = map_key(self.stack.last().unwrap()) {
+                        fail!(kind);
+                    }
+                }
+                //keys go in as they come, so a repeated one keeps its first position but the last value
+                Op::MakeMap(count) => {
+                    let flat = self.stack.split_off(self.stack.len() - 2 * count as usize);
+                    let mut entries = Vec::with_capacity(count as usize);
+                    let mut flat = flat.into_iter();
+                    while let (Some(key), Some(val)) = (flat.next(), flat.next()) {
+                        match map_key(&key) {
+                            Ok(key) => entries.push((key.to_string(), val)),
+                            Err(kind) => fail!(kind),
+                        }
+                    }
+                    self.stack.push(Value::map(entries));
+                }
+                Op::GetField(name) => {
+                    let target = self.pop();
+                    let Value::Text(name) = &proto.constants[name as usize] else {
+                        unreachable!("field names are always text")
+                    };
+                    push_result!(get_field(&target, name));
+                }
+                Op::SetField(name) => {
+                    let val = self.pop();
+                    let target = self.pop();
+                    let Value::Text(name) = &proto.constants[name as usize] else {
+                        unreachable!("field names are always text")
+                    };
+                    if let Err(kind) = set_field(&target, name, val) {
+                        fail!(kind);
+                    }
+                }
+                Op::Index => {
+                    let index = self.pop();
+                    let list = self.pop();
+                    push_result!(index_value(&list, &index));
+                }
+                Op::SetIndex => {
+                    let val = self.pop();
+                    let index = self.pop();
+                    let list = self.pop();
+                    if let Err(kind) = set_index(&list, &index, val) {
+                        fail!(kind);
+                    }
+                }
+                Op::Slice => {
+                    let to = self.pop();
+                    let from = self.pop();
+                    let list = self.pop();
+                    push_result!(slice_value(&list, &from, &to));
+                }
+                Op::GetUpvalue(index) => {
+                    let val = match &*upvalues[index as usize].borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(val) => val.clone(),
+                    };
+                    self.stack.push(val);
+                }
+                Op::SetUpvalue(index) => {
+                    let val = self.pop();
+                    match &mut *upvalues[index as usize].borrow_mut() {
+                        Upvalue::Open(slot) => self.stack[*slot] = val,
+                        Upvalue::Closed(closed) => *closed = val,
+                    }
+                }
+                Op::GetGlobalFunction(slot) => match &self.globals[slot as usize] {
+                    Some(val) => {
+                        let val = val.clone();
+                        self.stack.push(val);
+                    }
+                    None => fail!(RuntimeErrorKind::UndefinedFunction(cstate.global_name(slot))),
+                },
+                Op::Closure(index) => {
+                    let inner = proto.protos[index as usize].clone();
+                    let captured = inner
+                        .upvalues
+                        .iter()
+                        .map(|&(local, index)| {
+                            if local {
+                                self.capture_upvalue(base + index as usize)
+                            } else {
+                                upvalues[index as usize].clone()
+                            }
+                        })
+                        .collect();
+                    let closure = Closure {
+                        proto: inner,
+                        upvalues: captured,
+                    };
+                    self.stack.push(Value::Function(Function::new(Callable::Compiled(closure))));
+                }
+                Op::CloseUpvalues(slot) => self.close_upvalues(base + slot as usize),
+                Op::Call { argc } => call!(argc as usize),
+                Op::CallMethod { name, argc } => {
+                    let argc = argc as usize;
+                    let Value::Text(name) = &proto.constants[name as usize] else {
+                        unreachable!("method names are always text")
+                    };
+                    let at = self.stack.len() - argc - 1;
+                    match find_method(&self.stack[at], name) {
+                        //the thing becomes the first argument, with the method under it
+                        Ok((method, true)) => {
+                            self.stack.insert(at, method);
+                            call!(argc + 1)
+                        }
+                        Ok((function, false)) => {
+                            self.stack[at] = function;
+                            call!(argc)
+                        }
+                        Err(kind) => fail!(kind),
+                    }
+                }
+                Op::DefineStruct(index) => {
+                    let (name, fields) = &proto.structs[index as usize];
+                    declare_struct(&mut self.structs, name, fields);
+                }
+                Op::MakeStruct(index) => {
+                    let (name, fields) = &proto.structs[index as usize];
+                    let vals = self.stack.split_off(self.stack.len() - fields.len());
+                    push_result!(make_instance(&self.structs, name, fields, vals));
+                }
+                Op::DefineMethod { ty, name } => {
+                    let method = self.pop();
+                    let (Value::Text(ty), Value::Text(name)) =
+                        (&proto.constants[ty as usize], &proto.constants[name as usize])
+                    else {
+                        unreachable!("struct and method names are always text")
+                    };
+                    if let Err(kind) = define_method(&self.structs, ty, name, method) {
+                        fail!(kind);
+                    }
+                }
+                Op::Return => {
+                    let val = self.pop();
+                    if !self.openUpvalues.is_empty() {
+                        self.close_upvalues(base);
+                    }
+                    //the callee goes too
+                    self.stack.truncate(base - 1);
+                    self.frames.pop();
+                    if self.frames.len() == depth {
+                        return Ok(val);
+                    }
+                    let frame = &self.frames[self.frames.len() - 1];
+                    proto = frame.proto.clone();
+                    upvalues = frame.upvalues.clone();
+                    ip = frame.ip;
+                    base = frame.base;
+                    self.stack.push(val);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap_or(Value::None)
+    }
+}
@@ -0,0 +1,93 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+//a directory of its own for each test, since the repl leaves its history file wherever it's run
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("skcore-cli-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+//runs skcore in `dir` with `stdin` piped in, handing back its exit code and what it printed, colours and all
+fn skcore(dir: &Path, args: &[&str], stdin: &str) -> (i32, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_scriptinglang"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn the_repl_keeps_state_between_entries() {
+    let dir = scratch_dir("repl");
+    let input = "let x = 2;\nfn sq(n) {\n  n * n\n}\nsq(x) + 1\n:vars\n:fns\nnope\n:reset\n:vars\n:quit\n";
+    for engine in [&["-q"][..], &["-q", "--tree-walk"][..]] {
+        let (code, stdout, stderr) = skcore(&dir, engine, input);
+        assert_eq!(code, 0);
+        //the function went in over three lines, and :reset left nothing for the second :vars to list
+        assert_eq!(stdout, "5\nsq = <fn sq>\nx = 2\nsq(n)\neverything's been forgotten\n");
+        //a mistake doesn't end the session
+        assert!(stderr.contains("undefined variable `nope`"));
+    }
+    let history = std::fs::read_to_string(dir.join(".skcore_history")).unwrap();
+    assert!(history.contains(":fns"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn exit_codes_say_how_it_went() {
+    let dir = scratch_dir("exit-codes");
+    std::fs::write(dir.join("bad.sk"), "let = 1;\n").unwrap();
+    //0: it ran, or checked out
+    assert_eq!(
+        skcore(&dir, &["-q", "-e", "print(1 + 1);"], ""),
+        (0, "2\n".to_string(), String::new())
+    );
+    assert_eq!(skcore(&dir, &["-q", "check", "-e", "print(1);"], "").0, 0);
+    //1: the script had a problem, whether running it or parsing it
+    for args in [
+        &["-q", "-e", "1 / 0;"][..],
+        &["-q", "check", "bad.sk"][..],
+        &["-q", "bad.sk"][..],
+    ] {
+        let (code, stdout, stderr) = skcore(&dir, args, "");
+        assert_eq!(code, 1, "{:?}", args);
+        assert_eq!(stdout, "");
+        assert!(stderr.contains("error"));
+    }
+    //2: it never got as far as the script
+    for args in [&["--bogus"][..], &["-q", "missing.sk"][..], &["-q", "run"][..]] {
+        assert_eq!(skcore(&dir, args, "").0, 2, "{:?}", args);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn scripts_come_from_files_stdin_or_the_command_line() {
+    let dir = scratch_dir("inputs");
+    std::fs::write(dir.join("args.sk"), "print(argc()); print(arg(1));\n").unwrap();
+    assert_eq!(skcore(&dir, &["-q", "args.sk", "--", "a", "b"], "").1, "2\nb\n");
+    assert_eq!(skcore(&dir, &["-q", "-"], "print(\"piped\");").1, "piped\n");
+    assert_eq!(
+        skcore(&dir, &["-q", "ast", "-e", "let x = 1;"], "").1,
+        "Set\n  Variable x\n  Int 1\n"
+    );
+    let (_, tokens, _) = skcore(&dir, &["-q", "tokens", "-e", "let x"], "");
+    assert!(tokens.contains("KwLet") && tokens.contains("Identifier"));
+    //without -q there's the banner and the progress messages around the script's own output
+    let (_, stdout, _) = skcore(&dir, &["-e", "print(1);"], "");
+    assert!(stdout.contains("SKCore"));
+    assert!(stdout.ends_with("*** Executing... ***\n1\n"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,1308 @@
+use scriptinglang::ast::ASTNode;
+use scriptinglang::{Config, Error, Interpreter, ParseErrorKind, RuntimeError, RuntimeErrorKind, Value};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+//every test runs on both engines, since they're meant to be interchangeable
+fn interpreters() -> Vec<Interpreter> {
+    vec![
+        Interpreter::new(),
+        Interpreter::with_config(Config {
+            tree_walk: true,
+            ..Default::default()
+        }),
+    ]
+}
+
+#[test]
+fn eval_str_gives_back_the_last_value() {
+    for mut interpreter in interpreters() {
+        assert_eq!(interpreter.eval_str("1 + 2 * 3").unwrap(), Value::Int(7));
+        assert_eq!(
+            interpreter.eval_str("\"a\" + \"b\"").unwrap(),
+            Value::Text("ab".to_string())
+        );
+        assert_eq!(interpreter.eval_str("let x = 1;").unwrap(), Value::None);
+    }
+}
+
+#[test]
+fn state_carries_over_between_evals() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("let total = 10;").unwrap();
+        interpreter.eval_str("fn bump(n) { total += n; total }").unwrap();
+        assert_eq!(interpreter.eval_str("bump(5)").unwrap(), Value::Int(15));
+        assert_eq!(interpreter.get_global("total"), Some(Value::Int(15)));
+    }
+}
+
+#[test]
+fn call_function_runs_script_functions() {
+    for mut interpreter in interpreters() {
+        interpreter
+            .eval_str("fn greet(name, times) { let out = name; loop i in 1 to times { out += name; } return out; }")
+            .unwrap();
+        let greeting = interpreter.call_function("greet", &[Value::Text("hi".to_string()), Value::Int(3)]);
+        assert_eq!(greeting.unwrap(), Value::Text("hihihi".to_string()));
+    }
+}
+
+#[test]
+fn call_function_checks_the_call() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("fn one(x) { x }").unwrap();
+        match interpreter.call_function("nope", &[]) {
+            Err(Error::Call(RuntimeErrorKind::UndefinedFunction(id))) => assert_eq!(id, "nope"),
+            other => panic!("expected an undefined function, got {:?}", other),
+        }
+        match interpreter.call_function("one", &[]) {
+            Err(Error::Call(RuntimeErrorKind::ArityMismatch { expected, found, .. })) => {
+                assert_eq!((expected, found), (1, 0))
+            }
+            other => panic!("expected an arity mismatch, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn globals_can_be_set_from_outside() {
+    for mut interpreter in interpreters() {
+        interpreter.set_global("limit", Value::Int(4));
+        assert_eq!(interpreter.eval_str("limit * 2").unwrap(), Value::Int(8));
+        interpreter.eval_str("limit = 1;").unwrap();
+        assert_eq!(interpreter.get_global("limit"), Some(Value::Int(1)));
+        assert_eq!(interpreter.get_global("missing"), None);
+    }
+}
+
+#[test]
+fn syntax_errors_dont_run_anything() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("let x = 1;").unwrap();
+        match interpreter.eval_str("x = 2; let = ;") {
+            Err(Error::Syntax(errors)) => assert!(!errors.is_empty()),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+        assert_eq!(interpreter.get_global("x"), Some(Value::Int(1)));
+    }
+}
+
+#[test]
+fn runtime_errors_come_back_with_a_location() {
+    for mut interpreter in interpreters() {
+        let err = interpreter.eval_str("let a = 1;\nlet b = a / 0;").unwrap_err();
+        match &err {
+            Error::Runtime(err) => assert_eq!(err.kind, RuntimeErrorKind::DivisionByZero),
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+        assert!(interpreter.render_error(&err).contains("<eval>:2:9"));
+    }
+}
+
+#[test]
+fn eval_file_runs_files_and_reports_missing_ones() {
+    let path = std::env::temp_dir().join(format!("skcore-eval-file-{}.sk", std::process::id()));
+    std::fs::write(&path, "# a comment\nfn square(x) { x * x }\nsquare(9)\n").unwrap();
+    for mut interpreter in interpreters() {
+        assert_eq!(interpreter.eval_file(&path).unwrap(), Value::Int(81));
+        assert!(matches!(
+            interpreter.eval_file("does/not/exist.sk"),
+            Err(Error::Io { .. })
+        ));
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn script_args_are_visible_to_the_script() {
+    let mut interpreter = Interpreter::with_config(Config {
+        scriptArgs: vec!["first".to_string()],
+        ..Default::default()
+    });
+    assert_eq!(interpreter.eval_str("argc()").unwrap(), Value::Int(1));
+    assert_eq!(
+        interpreter.eval_str("arg(0)").unwrap(),
+        Value::Text("first".to_string())
+    );
+}
+
+#[test]
+fn registered_functions_can_be_called_from_scripts() {
+    for mut interpreter in interpreters() {
+        interpreter.register_fn("sumof", 3, |args| match args {
+            [Value::Int(a), Value::Int(b), Value::Int(c)] => Ok(Value::Int(a + b + c)),
+            _ => Err(RuntimeError::new(RuntimeErrorKind::Native(
+                "sumof wants numbers".to_string(),
+            ))),
+        });
+        assert_eq!(interpreter.eval_str("sumof(1, 2, 3) * 2").unwrap(), Value::Int(12));
+        assert_eq!(
+            interpreter.eval_str("fn twice(x) { sumof(x, x, 0) } twice(4)").unwrap(),
+            Value::Int(8)
+        );
+    }
+}
+
+#[test]
+fn registered_function_errors_point_at_the_call() {
+    for mut interpreter in interpreters() {
+        interpreter.register_fn("fail", 0, |_| {
+            Err(RuntimeError::new(RuntimeErrorKind::Native("nope".to_string())))
+        });
+        let err = interpreter
+            .eval_str(
+                "let x = 1;
+x + fail()",
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "nope");
+        assert!(interpreter.render_error(&err).contains("<eval>:2:5"));
+        match interpreter.eval_str("fail(1)") {
+            Err(Error::Runtime(err)) => assert!(matches!(err.kind, RuntimeErrorKind::ArityMismatch { .. })),
+            other => panic!("expected an arity mismatch, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn print_can_be_replaced() {
+    for mut interpreter in interpreters() {
+        let printed = Rc::new(RefCell::new(vec![]));
+        let sink = printed.clone();
+        interpreter.register_fn("print", 1, move |args| {
+            sink.borrow_mut().push(args[0].to_string());
+            Ok(Value::None)
+        });
+        interpreter.eval_str("print(\"a\"); print(1 + 1);").unwrap();
+        assert_eq!(*printed.borrow(), vec!["a".to_string(), "2".to_string()]);
+    }
+}
+
+//everything a program printed and how it ended, for holding the two engines up against each other
+fn run_capturing(mut interpreter: Interpreter, code: &str) -> (Vec<String>, Result<Value, String>) {
+    let printed = Rc::new(RefCell::new(vec![]));
+    let sink = printed.clone();
+    interpreter.register_fn("print", 1, move |args| {
+        sink.borrow_mut().push(args[0].to_string());
+        Ok(Value::None)
+    });
+    //the debug form of an error has where it happened and the stack trace, not just the message
+    let result = interpreter.eval_str(code).map_err(|err| format!("{:?}", err));
+    let printed = printed.borrow().clone();
+    (printed, result)
+}
+
+#[test]
+fn both_engines_run_programs_the_same() {
+    let programs = [
+        "fn checker(num) { if num % 15 == 0 { print(\"FizzBuzz\"); } else if num % 5 == 0 { print(\"Fizz\"); } \
+         else if num % 3 == 0 { print(\"Buzz\"); } else { print(num); } }\nloop num in 1 to 40 { checker(num); }",
+        "fn f(n) { if n % 15 == 0 { return 1; } return 0; }\nlet c = 0;\nloop i in 1 to 3000 { c = c + f(i); }\nc",
+        "fn makeCounter() { let count = 0; return fn() { count += 1; count }; }\nlet a = makeCounter();\n\
+         let b = makeCounter();\nprint(a()); print(a()); print(b());\n\
+         let fs = [];\nloop i in 0 to 3 { push(fs, fn() { i * 10 }); }\nfs[2]()",
+        "fn fib(n) { if n < 2 { return n; } fib(n - 1) + fib(n - 2) }\nprint(fib(15));\n\
+         let k = 0;\nloop while true { k += 1; if k % 2 == 0 { continue; } if k > 9 { break; } print(k); }",
+        "let xs = [3, 1, 2];\npush(xs, 7);\nlet m = {\"a\": xs, \"b\": 2.5};\nm.c = xs[1..3];\n\
+         print(m);\nprint(\"{len(xs)} {m.b * 2} {-7 / 2} {-7 % 3} {2 ** 10} {1 << 4 | 3}\");\nxs == [3, 1, 2, 7]",
+        "struct Point { x, y }\nimpl Point { fn norm(self) { self.x * self.x + self.y * self.y } }\n\
+         let p = Point { y: 4, x: 3 };\nprint(p);\np.x = 1;\nprint(p.norm());\nPoint { x: 1 }",
+        "fn down(n) { if n == 0 { return [][1]; } down(n - 1) }\ndown(3)",
+        "let total = 0;\nloop i in 0 to 5 { loop j in 0 to 5 { if j > i { break; } total += j; } }\nprint(total);\nundefined + 1",
+    ];
+    for code in programs {
+        let runs: Vec<_> = interpreters()
+            .into_iter()
+            .map(|interpreter| run_capturing(interpreter, code))
+            .collect();
+        assert_eq!(runs[0], runs[1], "the engines disagree on:\n{}", code);
+    }
+}
+
+#[test]
+fn operators_follow_precedence_and_associativity() {
+    let cases = [
+        ("1 + 2 * 3", 7),
+        ("2 * 3 + 1", 7),
+        ("(1 + 2) * 3", 9),
+        ("2 * (3 + 4) - -1", 15),
+        ("10 - 3 - 2", 5),
+        ("100 / 10 / 5", 2),
+        ("7 // 2 * 2", 6),
+        ("2 ** 3 ** 2", 512),
+        ("-2 ** 2", -4),
+        ("-(2 + 3) * 2", -10),
+        ("((((1))))", 1),
+    ];
+    for mut interpreter in interpreters() {
+        for (code, expected) in cases {
+            assert_eq!(interpreter.eval_str(code).unwrap(), Value::Int(expected), "{}", code);
+        }
+        assert_eq!(
+            interpreter.eval_str("1 + 2 < 4 && 3 > 2").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(interpreter.eval_str("!true == false").unwrap(), Value::Boolean(true));
+        assert_eq!(interpreter.eval_str("1 == 1 == true").unwrap(), Value::Boolean(true));
+        match interpreter.eval_str("(1 + 2") {
+            Err(Error::Syntax(errors)) => assert_eq!(errors[0].kind, ParseErrorKind::UnclosedParen),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+}
+
+//an error the way the command line shows it, without the colours
+fn rendered(interpreter: &Interpreter, err: &Error) -> String {
+    let text = interpreter.render_error(err);
+    let mut plain = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+#[test]
+fn errors_point_at_the_line_and_column_with_a_caret() {
+    for mut interpreter in interpreters() {
+        let err = interpreter
+            .eval_source("spans.sk", "let a = 1;\n  let = 3;\n")
+            .unwrap_err();
+        assert_eq!(
+            rendered(&interpreter, &err),
+            "error: expected an identifier, found `=`\n --> spans.sk:2:7\n  |\n2 |   let = 3;\n  |       ^\n\n\
+             aborting due to 1 syntax error(s)"
+        );
+        let err = interpreter
+            .eval_source("spans.sk", "let a = 1;\nfn f() {\n    a + nope\n}\nf();\n")
+            .unwrap_err();
+        assert_eq!(
+            rendered(&interpreter, &err),
+            "error: undefined variable `nope`\n --> spans.sk:3:9\n  |\n3 |     a + nope\n  |         ^^^^\n\
+             stack trace (most recent call last):\n  in `f`, called from spans.sk:5:1"
+        );
+        //the underline keeps the line's own tabs, so it lands under the code it means
+        let err = interpreter.eval_source("tabs.sk", "\t\tprint(1 / 0);\n").unwrap_err();
+        assert_eq!(
+            rendered(&interpreter, &err),
+            "error: division by zero\n --> tabs.sk:1:9\n  |\n1 | \t\tprint(1 / 0);\n  | \t\t      ^^^^^"
+        );
+    }
+}
+
+#[test]
+fn every_syntax_error_in_a_file_is_reported() {
+    for mut interpreter in interpreters() {
+        match interpreter.eval_str("let = 1;\nlet y 2;\nfn g( { }\nlet z = 3;\n5 = z;\nbreak;\nlet w = (1 + 2;") {
+            Err(Error::Syntax(errors)) => {
+                let kinds: Vec<&ParseErrorKind> = errors.iter().map(|err| &err.kind).collect();
+                assert!(matches!(kinds[0], ParseErrorKind::UnexpectedToken { .. }));
+                assert_eq!(kinds[1], &ParseErrorKind::MissingAssign);
+                assert!(matches!(kinds[2], ParseErrorKind::UnexpectedToken { .. }));
+                assert_eq!(kinds[3], &ParseErrorKind::InvalidAssignTarget);
+                assert_eq!(kinds[4], &ParseErrorKind::Misplaced("break".to_string()));
+                assert_eq!(kinds[5], &ParseErrorKind::UnclosedParen);
+                assert_eq!(kinds.len(), 6);
+            }
+            other => panic!("expected syntax errors, got {:?}", other),
+        }
+        match interpreter.eval_str("fn f() { 1") {
+            Err(Error::Syntax(errors)) => assert_eq!(errors[0].kind, ParseErrorKind::UnclosedBrace),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+        match interpreter.eval_str("let x =") {
+            Err(Error::Syntax(errors)) => assert!(matches!(errors[0].kind, ParseErrorKind::UnexpectedEof { .. })),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+        //none of that defined anything
+        assert_eq!(interpreter.get_global("z"), None);
+    }
+}
+
+#[test]
+fn runtime_errors_carry_the_script_stack() {
+    for mut interpreter in interpreters() {
+        let err = interpreter
+            .eval_source(
+                "stack.sk",
+                "fn inner(x) {\n  x / 0\n}\nfn outer() { inner(1) }\nouter();\n",
+            )
+            .unwrap_err();
+        match &err {
+            Error::Runtime(err) => {
+                assert_eq!(err.kind, RuntimeErrorKind::DivisionByZero);
+                let functions: Vec<&str> = err.stack.iter().map(|frame| frame.function.as_str()).collect();
+                assert_eq!(functions, ["outer", "inner"]);
+            }
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+        assert!(rendered(&interpreter, &err).ends_with(
+            "stack trace (most recent call last):\n  in `outer`, called from stack.sk:5:1\n  \
+             in `inner`, called from stack.sk:4:14"
+        ));
+    }
+}
+
+#[test]
+fn each_kind_of_runtime_error() {
+    let cases = [
+        ("nope(1)", RuntimeErrorKind::UndefinedFunction("nope".to_string())),
+        ("y + 1", RuntimeErrorKind::UndefinedVariable("y".to_string())),
+        (
+            "fn f(a) { a }\nf(1, 2)",
+            RuntimeErrorKind::ArityMismatch {
+                function: "f".to_string(),
+                expected: 1,
+                found: 2,
+            },
+        ),
+        (
+            "\"a\" - 1",
+            RuntimeErrorKind::TypeError("cannot apply `-` to text and int".to_string()),
+        ),
+        ("1 % 0", RuntimeErrorKind::DivisionByZero),
+    ];
+    for mut interpreter in interpreters() {
+        for (code, expected) in &cases {
+            match interpreter.eval_str(code) {
+                Err(Error::Runtime(err)) => assert_eq!(&err.kind, expected, "{}", code),
+                other => panic!("expected a runtime error from {:?}, got {:?}", code, other),
+            }
+        }
+    }
+}
+
+#[test]
+fn variables_hold_any_kind_of_value() {
+    for mut interpreter in interpreters() {
+        interpreter
+            .eval_str("let b = 3 > 2; let n = none; let f = 1.5; let x = 1; x = \"one\";")
+            .unwrap();
+        assert_eq!(interpreter.get_global("b"), Some(Value::Boolean(true)));
+        assert_eq!(interpreter.get_global("n"), Some(Value::None));
+        assert_eq!(interpreter.get_global("f"), Some(Value::Float(1.5)));
+        //there's only the one x, whatever it's holding
+        assert_eq!(interpreter.get_global("x"), Some(Value::Text("one".to_string())));
+        assert_eq!(interpreter.eval_str("x = b; x").unwrap(), Value::Boolean(true));
+        assert_eq!(
+            interpreter.eval_str("[b, n, f]").unwrap().to_string(),
+            "[true, none, 1.5]"
+        );
+    }
+}
+
+#[test]
+fn scopes_nest_and_assignment_finds_the_nearest_binding() {
+    for mut interpreter in interpreters() {
+        //a loop body changes the variable outside of it, rather than a copy
+        assert_eq!(
+            interpreter
+                .eval_str("let total = 0; loop i in 0 to 4 { total = total + i; } total")
+                .unwrap(),
+            Value::Int(6)
+        );
+        //let shadows for the rest of the block, and = changes the shadow
+        assert_eq!(
+            interpreter
+                .eval_str("let x = 1; let seen = 0; if true { let x = 2; x = 3; seen = x; } [x, seen]")
+                .unwrap(),
+            Value::list(vec![Value::Int(1), Value::Int(3)])
+        );
+        //functions can see and change globals
+        interpreter.eval_str("fn bump() { x = x + 10; } bump();").unwrap();
+        assert_eq!(interpreter.get_global("x"), Some(Value::Int(11)));
+        //but not the locals of whoever called them, and a block's locals are gone once it's over
+        for (code, id) in [
+            ("fn g() { let secret = 1; h() } fn h() { secret } g()", "secret"),
+            ("if true { let inner = 1; } inner", "inner"),
+        ] {
+            match interpreter.eval_str(code) {
+                Err(Error::Runtime(err)) => assert_eq!(err.kind, RuntimeErrorKind::UndefinedVariable(id.to_string())),
+                other => panic!("expected a runtime error, got {:?}", other),
+            }
+        }
+    }
+}
+
+#[test]
+fn break_continue_and_return_inside_nested_loops() {
+    for mut interpreter in interpreters() {
+        //return leaves the function from however deep it is
+        interpreter
+            .eval_str(
+                "fn find(target) { loop i in 1 to 10 { let j = 1; loop while j < 10 { \
+                 if i * j == target { return [i, j]; } j += 1; } } none }",
+            )
+            .unwrap();
+        assert_eq!(
+            interpreter.eval_str("find(12)").unwrap(),
+            Value::list(vec![Value::Int(2), Value::Int(6)])
+        );
+        assert_eq!(interpreter.eval_str("find(97)").unwrap(), Value::None);
+        //break only leaves the innermost loop
+        assert_eq!(
+            interpreter
+                .eval_str(
+                    "let pairs = 0; loop i in 0 to 5 { loop j in 0 to 5 { if j > i { break; } pairs += 1; } } pairs"
+                )
+                .unwrap(),
+            Value::Int(15)
+        );
+        //continue skips to the next go round, in both kinds of loop
+        assert_eq!(
+            interpreter
+                .eval_str("let odd = 0; loop i in 0 to 10 { if i % 2 == 0 { continue; } odd += i; } odd")
+                .unwrap(),
+            Value::Int(25)
+        );
+        assert_eq!(
+            interpreter
+                .eval_str(
+                    "let k = 0; let kept = []; \
+                     loop while k < 6 { k += 1; if k % 3 == 0 { continue; } push(kept, k); } kept"
+                )
+                .unwrap()
+                .to_string(),
+            "[1, 2, 4, 5]"
+        );
+    }
+}
+
+#[test]
+fn functions_can_be_called_before_theyre_declared() {
+    for mut interpreter in interpreters() {
+        let script = "
+            let answer = isEven(10);
+            fn isEven(n) { if n == 0 { return true; } return isOdd(n - 1); }
+            fn isOdd(n) { if n == 0 { return false; } return isEven(n - 1); }
+        ";
+        interpreter.eval_str(script).unwrap();
+        assert_eq!(interpreter.get_global("answer"), Some(Value::Boolean(true)));
+        assert_eq!(
+            interpreter.call_function("isOdd", &[Value::Int(7)]).unwrap(),
+            Value::Boolean(true)
+        );
+    }
+}
+
+#[test]
+fn calls_to_unknown_functions_fail_when_they_run() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("fn never() { missing (1) }").unwrap();
+        match interpreter.call_function("never", &[]) {
+            Err(Error::Runtime(err)) => {
+                assert_eq!(err.kind, RuntimeErrorKind::UndefinedFunction("missing".to_string()))
+            }
+            other => panic!("expected an undefined function, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn functions_are_values() {
+    for mut interpreter in interpreters() {
+        let script = "
+            fn apply(f, x) { f(x) }
+            fn twice(f) { fn(x) { f(f(x)) } }
+            let double = fn(x) { x * 2 };
+            let quad = twice(double);
+        ";
+        interpreter.eval_str(script).unwrap();
+        assert_eq!(interpreter.eval_str("apply(double, 4)").unwrap(), Value::Int(8));
+        assert_eq!(interpreter.eval_str("quad(3)").unwrap(), Value::Int(12));
+        assert_eq!(
+            interpreter.eval_str("apply(fn(x) { x - 1 }, 1)").unwrap(),
+            Value::Int(0)
+        );
+        assert_eq!(interpreter.eval_str("double").unwrap().to_string(), "<fn <anonymous>>");
+        assert_eq!(
+            interpreter.call_function("quad", &[Value::Int(1)]).unwrap(),
+            Value::Int(4)
+        );
+        let functions = interpreter.functions();
+        assert!(functions.iter().any(|(name, params)| name == "apply" && params.len() == 2));
+    }
+}
+
+#[test]
+fn closures_capture_variables_by_reference() {
+    for mut interpreter in interpreters() {
+        let script = "
+            fn counter() {
+                let count = 0;
+                let bump = fn() { count += 1; count };
+                let peek = fn() { count };
+                bump();
+                return fn(by) { loop i in 0 to by { bump(); } peek() };
+            }
+            let a = counter();
+            let b = counter();
+            a(2);
+        ";
+        interpreter.eval_str(script).unwrap();
+        assert_eq!(interpreter.eval_str("a(1)").unwrap(), Value::Int(4));
+        assert_eq!(interpreter.eval_str("b(0)").unwrap(), Value::Int(1));
+        interpreter.eval_str("let seen = 0; fn see() { seen = 5; }").unwrap();
+        interpreter.eval_str("see();").unwrap();
+        assert_eq!(interpreter.get_global("seen"), Some(Value::Int(5)));
+    }
+}
+
+#[test]
+fn higher_order_helpers_can_be_written_in_script() {
+    for mut interpreter in interpreters() {
+        let script = "
+            fn fold(f, acc, low, high) { loop i in low to high { acc = f(acc, i); } acc }
+            fn sumWhere(keep, high) { fold(fn(acc, n) { if keep(n) { return acc + n; } acc }, 0, 0, high) }
+            let limit = 3;
+            sumWhere(fn(n) { n > limit }, 7)
+        ";
+        assert_eq!(interpreter.eval_str(script).unwrap(), Value::Int(15));
+    }
+}
+
+#[test]
+fn each_loop_iteration_gets_its_own_variable() {
+    for mut interpreter in interpreters() {
+        let script = "
+            let first = none;
+            let last = none;
+            loop i in 0 to 3 {
+                let square = i * i;
+                if i == 0 { first = fn() { i + square }; }
+                last = fn() { i + square };
+            }
+            first() * 10 + last()
+        ";
+        assert_eq!(interpreter.eval_str(script).unwrap(), Value::Int(6));
+    }
+}
+
+#[test]
+fn calling_something_that_isnt_a_function_is_a_type_error() {
+    for mut interpreter in interpreters() {
+        match interpreter.eval_str("let x = 3;\nx(1)") {
+            Err(Error::Runtime(err)) => assert!(matches!(err.kind, RuntimeErrorKind::TypeError(_))),
+            other => panic!("expected a type error, got {:?}", other),
+        }
+        match interpreter.call_function("x", &[]) {
+            Err(Error::Call(RuntimeErrorKind::TypeError(_))) => {}
+            other => panic!("expected a type error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn lists_can_be_built_indexed_and_changed() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("let xs = [1, 2, 3];").unwrap();
+        assert_eq!(interpreter.eval_str("xs[0] + xs[-1]").unwrap(), Value::Int(4));
+        interpreter
+            .eval_str("xs[1] = 20; push(xs, 4); insert(xs, 0, 0);")
+            .unwrap();
+        assert_eq!(interpreter.eval_str("xs").unwrap().to_string(), "[0, 1, 20, 3, 4]");
+        assert_eq!(
+            interpreter.eval_str("remove(xs, 1) + pop(xs)").unwrap(),
+            Value::Int(5)
+        );
+        assert_eq!(interpreter.eval_str("len(xs)").unwrap(), Value::Int(3));
+        assert_eq!(interpreter.eval_str("xs[1..]").unwrap().to_string(), "[20, 3]");
+        assert_eq!(
+            interpreter.eval_str("xs[..-1] + [\"a\"]").unwrap().to_string(),
+            "[0, 20, \"a\"]"
+        );
+        let expected = vec![Value::Int(0), Value::Int(20), Value::Int(3)];
+        assert_eq!(interpreter.get_global("xs"), Some(Value::list(expected)));
+    }
+}
+
+#[test]
+fn lists_are_shared_not_copied() {
+    for mut interpreter in interpreters() {
+        let script = "
+            fn fill(list, n) { loop i in 0 to n { push(list, i); } }
+            let xs = [];
+            let ys = xs;
+            fill(ys, 3);
+            len(xs)
+        ";
+        assert_eq!(interpreter.eval_str(script).unwrap(), Value::Int(3));
+    }
+}
+
+#[test]
+fn loops_can_go_through_lists() {
+    for mut interpreter in interpreters() {
+        let script = "
+            let xs = [1, 2, 3, 4];
+            let total = 0;
+            loop x in xs {
+                push(xs, x);
+                if x == 2 { continue; }
+                total += x;
+            }
+            total * 100 + len(xs)
+        ";
+        assert_eq!(interpreter.eval_str(script).unwrap(), Value::Int(808));
+    }
+}
+
+#[test]
+fn bad_indexes_are_runtime_errors() {
+    for mut interpreter in interpreters() {
+        match interpreter.eval_str("let xs = [1, 2];\nxs[2]") {
+            Err(Error::Runtime(err)) => {
+                assert_eq!(err.kind, RuntimeErrorKind::IndexOutOfBounds { index: 2, length: 2 })
+            }
+            other => panic!("expected an out of bounds index, got {:?}", other),
+        }
+        match interpreter.eval_str("xs[\"a\"]") {
+            Err(Error::Runtime(err)) => assert!(matches!(err.kind, RuntimeErrorKind::TypeError(_))),
+            other => panic!("expected a type error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn maps_can_be_built_read_and_changed() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("let m = {\"a\": 1, \"b\": [2]};").unwrap();
+        assert_eq!(interpreter.eval_str("m[\"a\"] + m.b[0]").unwrap(), Value::Int(3));
+        interpreter
+            .eval_str("m.c = \"x\"; m[\"a\"] = 10; remove(m, \"b\");")
+            .unwrap();
+        assert_eq!(
+            interpreter.eval_str("m").unwrap().to_string(),
+            "{\"a\": 10, \"c\": \"x\"}"
+        );
+        assert_eq!(interpreter.eval_str("has(m, \"c\")").unwrap(), Value::Boolean(true));
+        assert_eq!(interpreter.eval_str("keys(m)").unwrap().to_string(), "[\"a\", \"c\"]");
+        let expected = Value::map([
+            ("a".to_string(), Value::Int(10)),
+            ("c".to_string(), Value::Text("x".to_string())),
+        ]);
+        assert_eq!(interpreter.get_global("m"), Some(expected));
+    }
+}
+
+#[test]
+fn loops_can_go_through_keys_and_values() {
+    for mut interpreter in interpreters() {
+        let script = "
+            let m = {\"x\": 1, \"y\": 2};
+            let out = \"-\";
+            loop k, v in m { out += k + v; }
+            loop k in m { out += k; }
+            loop i, x in [\"p\", \"q\"] { out += i + x; }
+            out
+        ";
+        assert_eq!(
+            interpreter.eval_str(script).unwrap(),
+            Value::Text("-x1y2xy0p1q".to_string())
+        );
+    }
+}
+
+#[test]
+fn missing_keys_are_runtime_errors() {
+    for mut interpreter in interpreters() {
+        match interpreter.eval_str("let m = {\"a\": 1};\nm.b") {
+            Err(Error::Runtime(err)) => assert_eq!(err.kind, RuntimeErrorKind::MissingKey("b".to_string())),
+            other => panic!("expected a missing key, got {:?}", other),
+        }
+        match interpreter.eval_str("m[1]") {
+            Err(Error::Runtime(err)) => assert!(matches!(err.kind, RuntimeErrorKind::TypeError(_))),
+            other => panic!("expected a type error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn a_bad_map_key_stops_the_literal_before_the_rest_of_it_runs() {
+    for mut interpreter in interpreters() {
+        let printed = Rc::new(RefCell::new(vec![]));
+        let sink = printed.clone();
+        interpreter.register_fn("print", 1, move |args| {
+            sink.borrow_mut().push(args[0].to_string());
+            Ok(Value::None)
+        });
+        match interpreter.eval_str("fn loud(v) { print(v); v }\n{\"a\": loud(1), 2: loud(2), \"c\": nope}") {
+            Err(Error::Runtime(err)) => assert_eq!(
+                err.kind,
+                RuntimeErrorKind::TypeError("map keys have to be text, not int".to_string())
+            ),
+            other => panic!("expected a type error, got {:?}", other),
+        }
+        //the value after the bad key never got worked out
+        assert_eq!(*printed.borrow(), vec!["1".to_string()]);
+    }
+}
+
+#[test]
+fn structs_have_fields_and_methods() {
+    for mut interpreter in interpreters() {
+        let script = "
+            struct Point { x, y }
+            impl Point {
+                fn len(self) { self.x * self.x + self.y * self.y }
+                fn moveby(self, dx) { self.x = self.x + dx; self }
+            }
+            let p = Point { y: 4, x: 3 };
+        ";
+        interpreter.eval_str(script).unwrap();
+        assert_eq!(interpreter.eval_str("p.len()").unwrap(), Value::Int(25));
+        interpreter.eval_str("p.moveby(1).moveby(1); p.y = 0;").unwrap();
+        assert_eq!(interpreter.eval_str("p").unwrap().to_string(), "Point { x: 5, y: 0 }");
+        assert_eq!(
+            interpreter.eval_str("p == Point { x: 5, y: 0 }").unwrap(),
+            Value::Boolean(true)
+        );
+    }
+}
+
+#[test]
+fn struct_literals_stay_out_of_if_and_loop_heads() {
+    for mut interpreter in interpreters() {
+        let script = "
+            struct Flag { on }
+            let p = 1;
+            let total = 0;
+            if p { total += 1; }
+            loop i in 0 to (Flag { on: 3 }).on { total += 10; }
+            total
+        ";
+        assert_eq!(interpreter.eval_str(script).unwrap(), Value::Int(31));
+    }
+}
+
+#[test]
+fn struct_mistakes_are_runtime_errors() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("struct Point { x, y }").unwrap();
+        let cases = [
+            ("Pt { x: 1 }", RuntimeErrorKind::UndefinedStruct("Pt".to_string())),
+            (
+                "Point { x: 1 }",
+                RuntimeErrorKind::MissingField {
+                    ty: "Point".to_string(),
+                    field: "y".to_string(),
+                },
+            ),
+            (
+                "Point { x: 1, y: 2 }.z",
+                RuntimeErrorKind::NoSuchField {
+                    ty: "Point".to_string(),
+                    field: "z".to_string(),
+                },
+            ),
+            (
+                "Point { x: 1, y: 2 }.len()",
+                RuntimeErrorKind::NoSuchMethod {
+                    ty: "Point".to_string(),
+                    method: "len".to_string(),
+                },
+            ),
+        ];
+        for (source, expected) in cases {
+            match interpreter.eval_str(source) {
+                Err(Error::Runtime(err)) => assert_eq!(err.kind, expected),
+                other => panic!("expected {:?}, got {:?}", expected, other),
+            }
+        }
+    }
+}
+
+#[test]
+fn ints_stay_whole_and_floats_take_over_when_mixed() {
+    for mut interpreter in interpreters() {
+        //an int and a float holding the same number are equal, so it's the printed form that says which one it is
+        let cases = [
+            ("16777216 + 1", "16777217"),
+            ("7 / 2 * 2", "6"),
+            ("7 / 2.0", "3.5"),
+            ("int(3.9) + int(\"4\")", "7"),
+            ("float(3)", "3.0"),
+        ];
+        for (code, expected) in cases {
+            assert_eq!(interpreter.eval_str(code).unwrap().to_string(), expected, "{}", code);
+        }
+    }
+}
+
+#[test]
+fn ints_and_floats_are_equal_inside_lists_and_maps_too() {
+    for mut interpreter in interpreters() {
+        for code in [
+            "1 == 1.0",
+            "[1, [2]] == [1.0, [2.0]]",
+            "{\"a\": 1, \"b\": [3]} == {\"b\": [3.0], \"a\": 1.0}",
+        ] {
+            assert_eq!(interpreter.eval_str(code).unwrap(), Value::Boolean(true), "{}", code);
+        }
+        assert_eq!(interpreter.eval_str("[1] != [1.5]").unwrap(), Value::Boolean(true));
+        //they're still two different constants
+        assert_eq!(
+            interpreter.eval_str("\"{1} {1.0}\"").unwrap(),
+            Value::Text("1 1.0".to_string())
+        );
+    }
+}
+
+#[test]
+fn int_overflow_is_a_runtime_error() {
+    for mut interpreter in interpreters() {
+        match interpreter.eval_str("9223372036854775807 + 1") {
+            Err(Error::Runtime(err)) => assert_eq!(err.kind, RuntimeErrorKind::IntegerOverflow("+".to_string())),
+            other => panic!("expected an overflow, got {:?}", other),
+        }
+        match interpreter.eval_str("int(\"lots\")") {
+            Err(Error::Runtime(err)) => assert!(matches!(err.kind, RuntimeErrorKind::TypeError(_))),
+            other => panic!("expected a type error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn strings_have_escapes_and_raw_and_multiline_forms() {
+    for mut interpreter in interpreters() {
+        assert_eq!(interpreter.eval_str("\"\"").unwrap(), Value::Text(String::new()));
+        assert_eq!(
+            interpreter.eval_str(r#""tab\there \"quoted\" \\ \u{e9}\n""#).unwrap(),
+            Value::Text("tab\there \"quoted\" \\ é\n".to_string())
+        );
+        assert_eq!(
+            interpreter.eval_str(r#"r"C:\new""#).unwrap(),
+            Value::Text("C:\\new".to_string())
+        );
+        assert_eq!(
+            interpreter.eval_str("\"one\ntwo\"").unwrap(),
+            Value::Text("one\ntwo".to_string())
+        );
+    }
+}
+
+#[test]
+fn bad_strings_are_syntax_errors() {
+    for mut interpreter in interpreters() {
+        match interpreter.eval_str("let s = \"oops;") {
+            Err(Error::Syntax(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedString);
+                assert_eq!(errors[0].span.end - errors[0].span.start, 1);
+            }
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+        match interpreter.eval_str(r#""\q""#) {
+            Err(Error::Syntax(errors)) => assert_eq!(errors[0].kind, ParseErrorKind::InvalidEscape("\\q".to_string())),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn text_can_interpolate_expressions() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("let l = 3; let m = {\"k\": [1, 2]};").unwrap();
+        assert_eq!(
+            interpreter
+                .eval_str(r#""iter {l} of {l * 2}, {m["k"]} {{x}}""#)
+                .unwrap(),
+            Value::Text("iter 3 of 6, [1, 2] {x}".to_string())
+        );
+        assert_eq!(
+            interpreter
+                .eval_str(r#""[{3.14159:.2}] [{l:>4}] [{l:03}] [{"ab":^4}]""#)
+                .unwrap(),
+            Value::Text("[3.14] [   3] [003] [ ab ]".to_string())
+        );
+    }
+}
+
+#[test]
+fn bad_interpolations_are_syntax_errors() {
+    for mut interpreter in interpreters() {
+        let cases = [
+            ("\"{}\"", None),
+            ("\"{1 +}\"", None),
+            ("\"{1:q}\"", Some(ParseErrorKind::InvalidFormatSpec("q".to_string()))),
+            (
+                "\"{x\";\nlet y = \"}\";",
+                Some(ParseErrorKind::UnterminatedInterpolation),
+            ),
+        ];
+        for (source, expected) in cases {
+            match interpreter.eval_str(source) {
+                Err(Error::Syntax(errors)) => {
+                    if let Some(expected) = expected {
+                        assert_eq!(errors[0].kind, expected);
+                    }
+                }
+                other => panic!("expected a syntax error, got {:?}", other),
+            }
+        }
+    }
+}
+
+#[test]
+fn and_or_short_circuit_and_give_back_a_side() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("let a = 2; let b = 1;").unwrap();
+        assert_eq!(interpreter.eval_str("a > 1 && b < 2").unwrap(), Value::Boolean(true));
+        assert_eq!(
+            interpreter.eval_str("a > 5 or not b == 2").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            interpreter.eval_str("none or \"default\"").unwrap(),
+            Value::Text("default".to_string())
+        );
+        //the right side of each of these would be an error if it ran
+        assert_eq!(interpreter.eval_str("0 and missing()").unwrap(), Value::Int(0));
+        assert_eq!(interpreter.eval_str("a || missing()").unwrap(), Value::Int(2));
+        assert_eq!(interpreter.eval_str("!a == false").unwrap(), Value::Boolean(true));
+    }
+}
+
+#[test]
+fn if_and_loop_while_agree_on_truthiness() {
+    for mut interpreter in interpreters() {
+        let script = "let seen = [];
+            loop n in 0 to 7 {
+                let val = [0, 3, \"\", \"x\", [], [1], none][n];
+                let went = false;
+                loop while val { went = true; val = false; }
+                if val { push(seen, \"bad\"); }
+                if went { push(seen, n); }
+            }
+            seen";
+        let expected = vec![Value::Int(1), Value::Int(3), Value::Int(5)];
+        assert_eq!(interpreter.eval_str(script).unwrap(), Value::list(expected));
+    }
+}
+
+#[test]
+fn compound_assignment_works_on_variables_elements_and_fields() {
+    for mut interpreter in interpreters() {
+        let script = "struct P { x }
+            let n = 10; n *= 3; n /= 4; n %= 5; n **= 3;
+            let s = \"a\"; s += \"b\";
+            let xs = [1, [2]]; xs[0] += 10; xs[1][0] -= 5;
+            let m = {\"k\": 2}; m[\"k\"] **= 2; m.k += 1;
+            let p = P { x: 1 }; p.x -= 3;
+            [n, s, xs, m.k, p.x]";
+        let expected = Value::list(vec![
+            Value::Int(8),
+            Value::Text("ab".to_string()),
+            Value::list(vec![Value::Int(11), Value::list(vec![Value::Int(-3)])]),
+            Value::Int(5),
+            Value::Int(-2),
+        ]);
+        assert_eq!(interpreter.eval_str(script).unwrap(), expected);
+    }
+}
+
+#[test]
+fn compound_assignment_needs_its_target_to_exist() {
+    for mut interpreter in interpreters() {
+        interpreter.eval_str("let m = {}; let xs = [];").unwrap();
+        let cases = [
+            ("nope += 1;", RuntimeErrorKind::UndefinedVariable("nope".to_string())),
+            ("m[\"k\"] *= 2;", RuntimeErrorKind::MissingKey("k".to_string())),
+            (
+                "xs[0] -= 1;",
+                RuntimeErrorKind::IndexOutOfBounds { index: 0, length: 0 },
+            ),
+        ];
+        for (source, expected) in cases {
+            match interpreter.eval_str(source) {
+                Err(Error::Runtime(err)) => assert_eq!(err.kind, expected),
+                other => panic!("expected {:?}, got {:?}", expected, other),
+            }
+        }
+    }
+}
+
+#[test]
+fn power_floor_division_and_bitwise_operators() {
+    for mut interpreter in interpreters() {
+        let cases = [
+            ("2 ** 3 ** 2", Value::Int(512)),
+            ("-2 ** 2", Value::Int(-4)),
+            ("2 ** -1", Value::Float(0.5)),
+            ("-7 // 2", Value::Int(-4)),
+            ("7.5 // 2", Value::Float(3.0)),
+            ("6 & 3 | 8 ^ 1", Value::Int(11)),
+            ("~5", Value::Int(-6)),
+            ("1 + 2 << 1", Value::Int(6)),
+            ("-16 >> 2", Value::Int(-4)),
+            ("10 & 1 == 0", Value::Boolean(true)),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(interpreter.eval_str(source).unwrap(), expected, "{}", source);
+        }
+    }
+}
+
+#[test]
+fn bitwise_operators_only_take_ints() {
+    for mut interpreter in interpreters() {
+        for source in ["1.5 & 1", "~1.5", "1 << -1"] {
+            match interpreter.eval_str(source) {
+                Err(Error::Runtime(err)) => assert!(matches!(err.kind, RuntimeErrorKind::TypeError(_))),
+                other => panic!("expected a type error from {}, got {:?}", source, other),
+            }
+        }
+        match interpreter.eval_str("1 << 64") {
+            Err(Error::Runtime(err)) => assert_eq!(err.kind, RuntimeErrorKind::IntegerOverflow("<<".to_string())),
+            other => panic!("expected an overflow, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn comments_can_go_anywhere() {
+    for mut interpreter in interpreters() {
+        let source = "let x = 1; # one\n/* a /* nested */ block\n*/ x + /* two */ 2 # three";
+        assert_eq!(interpreter.eval_str(source).unwrap(), Value::Int(3));
+        assert_eq!(
+            interpreter.eval_str("\"# /* */\"").unwrap(),
+            Value::Text("# /* */".to_string())
+        );
+        //a comment doesn't throw off where errors point
+        let err = interpreter.eval_str("/* a\nb */ 1 + none").unwrap_err();
+        assert!(interpreter.render_error(&err).contains("<eval>:2:6"));
+        match interpreter.eval_str("1 /* /* */") {
+            Err(Error::Syntax(errors)) => assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedComment),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn doc_comments_belong_to_the_next_function() {
+    let mut interpreter = Interpreter::new();
+    let trees = interpreter
+        .parse(
+            "<doc>",
+            "## not this one\nlet x = 1;\n## squares\n##  a number\nfn square(x) { x * x }",
+        )
+        .unwrap();
+    assert!(matches!(&trees[1].ast, ASTNode::FunctionDecl(_, _, _, Some(doc)) if doc == "squares\n a number"));
+    let trees = interpreter.parse("<doc>", "fn plain() {}").unwrap();
+    assert!(matches!(&trees[0].ast, ASTNode::FunctionDecl(_, _, _, None)));
+}
+
+#[test]
+fn imports_run_a_module_once_and_share_its_top_level() {
+    let dir = std::env::temp_dir().join(format!("skcore-imports-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(
+        dir.join("util.sk"),
+        "let loads = 0;\nfn limit() { 10 }\nfn checker(x) { x > limit() }\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("sub/inner.sk"),
+        "import \"../util.sk\" as u;\nlet value = u.limit() * 2;\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("main.sk"),
+        "import \"util.sk\" as util;\nfrom \"util.sk\" import checker;\nimport \"sub/inner.sk\" as inner;\n\
+         fn limit() { 0 }\n[util.checker(11), checker(5), inner.value, util.loads]\n",
+    )
+    .unwrap();
+    for mut interpreter in interpreters() {
+        assert_eq!(
+            interpreter.eval_file(dir.join("main.sk")).unwrap(),
+            Value::list(vec![
+                Value::Boolean(true),
+                Value::Boolean(false),
+                Value::Int(20),
+                Value::Int(0)
+            ])
+        );
+        //the module's own globals stay in the module
+        assert_eq!(interpreter.get_global("loads"), None);
+        assert!(interpreter.get_global("util").is_some());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bad_imports_are_errors() {
+    let dir = std::env::temp_dir().join(format!("skcore-bad-imports-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.sk"), "import \"b.sk\" as b;\n").unwrap();
+    std::fs::write(dir.join("b.sk"), "import \"a.sk\" as a;\n").unwrap();
+    std::fs::write(dir.join("missing.sk"), "import \"nowhere.sk\" as n;\n").unwrap();
+    std::fs::write(dir.join("d.sk"), "let d = 1;\n").unwrap();
+    std::fs::write(dir.join("unknown.sk"), "from \"d.sk\" import c;\n").unwrap();
+    std::fs::write(dir.join("binary.sk"), [0xff, 0xfe, 0x00]).unwrap();
+    std::fs::write(dir.join("unreadable.sk"), "let x = 1;\nimport \"binary.sk\" as b;\n").unwrap();
+    for mut interpreter in interpreters() {
+        match interpreter.eval_file(dir.join("a.sk")) {
+            Err(Error::Runtime(err)) => match err.kind {
+                RuntimeErrorKind::ImportCycle(files) => assert_eq!(files.len(), 3),
+                other => panic!("expected an import cycle, got {:?}", other),
+            },
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+        match interpreter.eval_file(dir.join("missing.sk")) {
+            Err(Error::Runtime(err)) => {
+                assert_eq!(err.kind, RuntimeErrorKind::ModuleNotFound("nowhere.sk".to_string()))
+            }
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+        match interpreter.eval_file(dir.join("unknown.sk")) {
+            Err(Error::Runtime(err)) => assert_eq!(
+                err.kind,
+                RuntimeErrorKind::NoSuchExport {
+                    module: "d.sk".to_string(),
+                    name: "c".to_string()
+                }
+            ),
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+        //a module that's there but isn't text points at the import, just like one that isn't there at all
+        let err = interpreter.eval_file(dir.join("unreadable.sk")).unwrap_err();
+        match &err {
+            Error::Runtime(err) => assert!(matches!(
+                &err.kind,
+                RuntimeErrorKind::ModuleUnreadable { path, .. } if path == "binary.sk"
+            )),
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+        assert!(rendered(&interpreter, &err).contains("unreadable.sk:2:1"));
+        match interpreter.eval_str("fn f() { import \"a.sk\" as a; }") {
+            Err(Error::Syntax(errors)) => assert_eq!(errors[0].kind, ParseErrorKind::Misplaced("import".to_string())),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_module_only_sees_its_own_globals() {
+    let dir = std::env::temp_dir().join(format!("skcore-module-globals-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("m.sk"),
+        "let size = len([1, 2]);\nfn peek() { secret }\nfn poke() { secret = 2; }\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("main.sk"), "import \"m.sk\" as m;\nlet secret = 1;\n").unwrap();
+    for mut interpreter in interpreters() {
+        interpreter.eval_file(dir.join("main.sk")).unwrap();
+        //the natives are there, but the importer's globals aren't - not to read, and not to assign
+        assert_eq!(interpreter.eval_str("m.size").unwrap(), Value::Int(2));
+        for call in ["m.peek()", "m.poke()"] {
+            match interpreter.eval_str(call) {
+                Err(Error::Runtime(err)) => {
+                    assert_eq!(err.kind, RuntimeErrorKind::UndefinedVariable("secret".to_string()))
+                }
+                other => panic!("expected a runtime error, got {:?}", other),
+            }
+        }
+        assert_eq!(interpreter.get_global("secret"), Some(Value::Int(1)));
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_module_has_its_own_structs() {
+    let dir = std::env::temp_dir().join(format!("skcore-module-structs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("shapes.sk"),
+        "struct P { name }\nimpl P { fn who(self) { \"shapes {self.name}\" } }\nfn make(n) { P { name: n } }\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("main.sk"),
+        "import \"shapes.sk\" as shapes;\nstruct P { x }\nimpl P { fn who(self) { \"main {self.x}\" } }\n\
+         let theirs = shapes.make(\"a\");\nlet ours = P { x: 1 };\n[theirs.who(), ours.who(), \"{theirs}\", theirs == ours]\n",
+    )
+    .unwrap();
+    for mut interpreter in interpreters() {
+        assert_eq!(
+            interpreter.eval_file(dir.join("main.sk")).unwrap(),
+            Value::list(vec![
+                Value::Text("shapes a".to_string()),
+                Value::Text("main 1".to_string()),
+                Value::Text("P { name: \"a\" }".to_string()),
+                Value::Boolean(false),
+            ])
+        );
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn lists_maps_and_structs_that_hold_themselves() {
+    for mut interpreter in interpreters() {
+        interpreter
+            .eval_str("let xs = [1]; push(xs, xs); let ys = [1]; push(ys, ys); let m = {}; m.me = m;")
+            .unwrap();
+        assert_eq!(
+            interpreter.eval_str("\"{xs} {m}\"").unwrap(),
+            Value::Text("[1, [...]] {\"me\": {...}}".to_string())
+        );
+        assert_eq!(interpreter.eval_str("xs == ys").unwrap(), Value::Boolean(true));
+        assert_eq!(interpreter.eval_str("xs == [1, [2]]").unwrap(), Value::Boolean(false));
+        let xs = interpreter.get_global("xs").unwrap();
+        assert_eq!(xs.to_string(), "[1, [...]]");
+        assert_eq!(format!("{:?}", xs), "List([1, [...]])");
+        interpreter
+            .eval_str("struct Node { next } let n = Node { next: none }; n.next = n;")
+            .unwrap();
+        assert_eq!(
+            interpreter.eval_str("\"{n}\"").unwrap(),
+            Value::Text("Node { next: Node {...} }".to_string())
+        );
+    }
+}
+
+#[test]
+fn a_broken_literal_is_one_error() {
+    for mut interpreter in interpreters() {
+        for source in [
+            "fn f() {\n let m = {\"a\" 1};\n let y = 2;\n}",
+            "let m = {\"a\" 1};\nlet y = 2;",
+            "struct P { a }\nfn f() {\n let p = P { a 1 };\n}",
+        ] {
+            match interpreter.eval_str(source) {
+                Err(Error::Syntax(errors)) => assert_eq!(errors.len(), 1, "{}: {:?}", source, errors),
+                other => panic!("expected a syntax error, got {:?}", other),
+            }
+        }
+    }
+}
+
+#[test]
+fn hosts_can_look_inside_functions_structs_and_modules() {
+    for mut interpreter in interpreters() {
+        let val = interpreter
+            .eval_str("struct P { x, y } fn add(a, b) { a + b } P { x: 1, y: add }")
+            .unwrap();
+        let instance = match val {
+            Value::Struct(instance) => instance,
+            other => panic!("expected a struct, got {:?}", other),
+        };
+        let instance = instance.borrow();
+        assert_eq!(instance.type_name(), "P");
+        assert_eq!(instance.field("x"), Some(&Value::Int(1)));
+        assert_eq!(instance.fields().map(|(name, _)| name).collect::<Vec<_>>(), ["x", "y"]);
+        let function: &scriptinglang::Function = match instance.field("y") {
+            Some(Value::Function(function)) => function,
+            other => panic!("expected a function, got {:?}", other),
+        };
+        assert_eq!(function.name(), "add");
+        assert_eq!(function.arity(), 2);
+        assert_eq!(function.params(), Some(&["a".to_string(), "b".to_string()][..]));
+    }
+}
@@ -0,0 +1,76 @@
+use scriptinglang::ast::Span;
+use scriptinglang::lexer::{lex_source, Token};
+use scriptinglang::{ParseError, ParseErrorKind};
+
+//the tokens some code lexes to, and whatever the lexer had to say about it
+fn lex(code: &str) -> (Vec<(Token, String)>, Vec<ParseError>) {
+    let mut errors = vec![];
+    let tokens = lex_source(code, 0, &mut errors)
+        .into_iter()
+        .map(|tok| (tok.token, tok.text))
+        .collect();
+    (tokens, errors)
+}
+
+#[test]
+fn a_string_ending_in_a_backslash_is_unterminated() {
+    let (tokens, errors) = lex("\"abc\\");
+    assert!(tokens.is_empty());
+    assert_eq!(
+        errors,
+        [ParseError::new(ParseErrorKind::UnterminatedString, Span::new(0, 1))]
+    );
+    //after something else, the error still points at the string's opening quote
+    let (_, errors) = lex("let s = \"abc\\");
+    assert_eq!(
+        errors,
+        [ParseError::new(ParseErrorKind::UnterminatedString, Span::new(8, 9))]
+    );
+}
+
+#[test]
+fn an_unclosed_interpolation_stops_at_the_strings_closing_quote() {
+    let (tokens, errors) = lex("\"{x\"\nlet y = \"}\";");
+    assert_eq!(
+        errors,
+        [ParseError::new(
+            ParseErrorKind::UnterminatedInterpolation,
+            Span::new(1, 2)
+        )]
+    );
+    //the code after the broken string is lexed the same as ever
+    let kinds: Vec<Token> = tokens.into_iter().map(|(token, _)| token).collect();
+    assert_eq!(
+        kinds,
+        [
+            Token::KwLet,
+            Token::Identifier,
+            Token::OpAssign,
+            Token::Text,
+            Token::KwTerminator
+        ]
+    );
+    let (_, errors) = lex("\"{x\"");
+    assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedInterpolation);
+}
+
+#[test]
+fn an_unclosed_unicode_escape_stops_at_the_strings_closing_quote() {
+    let (tokens, errors) = lex("\"\\u{41\" + \"}\"");
+    assert!(errors.is_empty());
+    assert_eq!(
+        tokens,
+        [
+            (Token::Text, "\"\\u{41\"".to_string()),
+            (Token::OpAdd, "+".to_string()),
+            (Token::Text, "\"}\"".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn strings_inside_interpolations_are_skipped_over_whole() {
+    let (tokens, errors) = lex("\"{join(xs, \", \")} and {\"}\"}\"");
+    assert!(errors.is_empty());
+    assert_eq!(tokens.len(), 1);
+}